@@ -1,19 +1,34 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Decimal,
-    Uint128,
+    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+    Decimal, Uint128,
 };
 use cw2::set_contract_version;
+use regen_types::validate_weights;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use crate::state::{Config, State, ValidatorInfo, CONFIG, STATE, VALIDATORS};
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::state::{
+    rebuild_validator_cache, save_state, save_validator, Config, State, ValidatorInfo,
+    ValidatorTotals, CONFIG, VALIDATOR_TOTALS,
+};
 use crate::execute::{
-    execute_claim_rewards, execute_claim_unbonding, execute_pause, execute_rebalance, execute_resume,
-    execute_stake, execute_unbond, execute_update_config, execute_update_validators,
+    execute_add_hook, execute_apply_registry_update, execute_claim_rewards,
+    execute_claim_unbonding, execute_drain_validators, execute_pause, execute_rebalance,
+    execute_rebuild_validator_cache, execute_reconcile_slashing, execute_registry_update,
+    execute_remove_hook, execute_resume,
+    execute_set_validator_tags, execute_stake, execute_stake_tokenized_shares,
+    execute_sync_validators, execute_unbond, execute_update_config,
+    execute_update_decentralization, execute_update_diversity_constraints,
+    execute_update_validators, handle_redeem_shares_reply, handle_registry_reply,
+    new_validator_info, validate_limits, validate_stake_routing, REDEEM_SHARES_REPLY_ID,
+    REGISTRY_REPLY_ID,
 };
+use crate::helpers::validate_validator;
+use crate::hooks::{handle_hook_reply, HOOK_REPLY_ID};
 use crate::query::{
-    query_config, query_exchange_rate, query_simulate_stake, query_simulate_unbond, query_state,
-    query_unbonding, query_validators,
+    query_config, query_decentralization, query_diversity_constraints, query_exchange_rate,
+    query_hooks, query_rate_history, query_simulate_stake, query_simulate_unbond, query_state,
+    query_twap_rate, query_unbonding, query_validator_cache, query_validators,
 };
 
 const CONTRACT_NAME: &str = "crates.io:regen-liquid-staking";
@@ -44,6 +59,16 @@ pub fn instantiate(
         admin.clone()
     };
 
+    let validator_registry = msg
+        .validator_registry
+        .map(|r| deps.api.addr_validate(&r))
+        .transpose()?;
+
+    let rewards_contract = msg
+        .rewards_contract
+        .map(|r| deps.api.addr_validate(&r))
+        .transpose()?;
+
     let config = Config {
         admin: admin.clone(),
         dregen_token: dregen_token_addr,
@@ -52,8 +77,20 @@ pub fn instantiate(
         max_validators: msg.max_validators,
         min_delegation: msg.min_delegation,
         pause_contract: false,
+        validator_registry,
+        rewards_contract,
+        drain_batch: msg.drain_batch,
+        delegation_strategy: msg.delegation_strategy.unwrap_or_default(),
+        stake_routing: msg.stake_routing,
+        max_unbond_validators: msg.max_unbond_validators,
+        slashing_dust: msg.slashing_dust,
     };
 
+    if let Some(routing) = &config.stake_routing {
+        validate_stake_routing(routing)?;
+    }
+    validate_limits(config.drain_batch, config.max_unbond_validators)?;
+
     // Initialize contract state
     let state = State {
         total_regen_staked: Uint128::zero(),
@@ -65,22 +102,18 @@ pub fn instantiate(
     };
 
     CONFIG.save(deps.storage, &config)?;
-    STATE.save(deps.storage, &state)?;
+    save_state(deps.storage, &state, env.block.time)?;
+    VALIDATOR_TOTALS.save(deps.storage, &ValidatorTotals::default())?;
 
     // Initialize validators
+    validate_weights(&msg.validators, msg.max_validators as usize, false)?;
     for validator_param in msg.validators {
-        let _validator_addr = deps.api.addr_validate(&validator_param.address)?;
+        let validator = validate_validator(deps.as_ref(), &validator_param.address)?;
         let validator_info = ValidatorInfo {
-            address: validator_param.address,
-            delegated_amount: Uint128::zero(),
-            weight: validator_param.weight,
-            last_reward_claim: env.block.time,
-            slashing_events: 0,
-            uptime_percentage: Decimal::percent(100),
-            commission_rate: Decimal::percent(10), // Default commission
-            is_active: true,
+            commission_rate: validator.commission,
+            ..new_validator_info(validator_param.address, validator_param.weight, env.block.time)
         };
-        VALIDATORS.save(deps.storage, validator_info.address.clone(), &validator_info)?;
+        save_validator(deps.storage, &validator_info)?;
     }
 
     Ok(Response::new()
@@ -99,15 +132,35 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::Stake {} => execute_stake(deps, env, info),
+        ExecuteMsg::StakeTokenizedShares {} => execute_stake_tokenized_shares(deps, env, info),
         ExecuteMsg::Unbond { dregen_amount } => execute_unbond(deps, env, info, dregen_amount),
         ExecuteMsg::ClaimUnbonding { unbonding_id } => {
             execute_claim_unbonding(deps, env, info, unbonding_id)
         }
         ExecuteMsg::Rebalance {} => execute_rebalance(deps, env, info),
+        ExecuteMsg::DrainValidators {} => execute_drain_validators(deps, env),
+        ExecuteMsg::SyncValidators {} => execute_sync_validators(deps),
+        ExecuteMsg::ReconcileSlashing {} => execute_reconcile_slashing(deps, env),
         ExecuteMsg::ClaimRewards {} => execute_claim_rewards(deps, env, info),
         ExecuteMsg::UpdateValidators { validators } => {
             execute_update_validators(deps, env, info, validators)
         }
+        ExecuteMsg::RegistryUpdate { validators } => {
+            execute_registry_update(deps, info, validators)
+        }
+        ExecuteMsg::ApplyRegistryUpdate {} => execute_apply_registry_update(deps, env),
+        ExecuteMsg::SetValidatorTags { address, tags } => {
+            execute_set_validator_tags(deps, env, info, address, tags)
+        }
+        ExecuteMsg::UpdateDiversityConstraints { constraints } => {
+            execute_update_diversity_constraints(deps, info, constraints)
+        }
+        ExecuteMsg::UpdateDecentralization { params } => {
+            execute_update_decentralization(deps, info, params)
+        }
+        ExecuteMsg::RebuildValidatorCache {} => execute_rebuild_validator_cache(deps, info),
+        ExecuteMsg::AddHook { contract } => execute_add_hook(deps, info, contract),
+        ExecuteMsg::RemoveHook { contract } => execute_remove_hook(deps, info, contract),
         ExecuteMsg::Pause {} => execute_pause(deps, env, info),
         ExecuteMsg::Resume {} => execute_resume(deps, env, info),
         ExecuteMsg::UpdateConfig {
@@ -115,21 +168,675 @@ pub fn execute(
             fee_rate,
             max_validators,
             dregen_token,
-        } => execute_update_config(deps, env, info, admin, fee_rate, max_validators, dregen_token),
+            validator_registry,
+            rewards_contract,
+            drain_batch,
+            delegation_strategy,
+            stake_routing,
+            max_unbond_validators,
+            slashing_dust,
+        } => execute_update_config(
+            deps,
+            env,
+            info,
+            admin,
+            fee_rate,
+            max_validators,
+            dregen_token,
+            validator_registry,
+            rewards_contract,
+            drain_batch,
+            delegation_strategy,
+            stake_routing,
+            max_unbond_validators,
+            slashing_dust,
+        ),
+    }
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        REDEEM_SHARES_REPLY_ID => handle_redeem_shares_reply(deps, env),
+        HOOK_REPLY_ID => handle_hook_reply(msg),
+        REGISTRY_REPLY_ID => handle_registry_reply(msg),
+        id => Err(ContractError::UnknownReplyId { id }),
     }
 }
 
+/// Rebuild the validator cache on every code upgrade, so state written before the cache existed
+/// gets its totals and active set without waiting for the admin
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let totals = rebuild_validator_cache(deps.storage)?;
+    Ok(Response::new()
+        .add_attribute("method", "migrate")
+        .add_attribute("contract_version", CONTRACT_VERSION)
+        .add_attribute("active", totals.active_count.to_string())
+        .add_attribute("total_delegated", totals.total_delegated))
+}
+
 #[entry_point]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
         QueryMsg::State {} => to_binary(&query_state(deps)?),
         QueryMsg::ExchangeRate {} => to_binary(&query_exchange_rate(deps, env)?),
-        QueryMsg::Validators {} => to_binary(&query_validators(deps)?),
+        QueryMsg::Validators { start_after, limit } => {
+            to_binary(&query_validators(deps, start_after, limit)?)
+        }
         QueryMsg::Unbonding { user } => to_binary(&query_unbonding(deps, user)?),
         QueryMsg::SimulateStake { amount } => to_binary(&query_simulate_stake(deps, env, amount)?),
         QueryMsg::SimulateUnbond { dregen_amount } => {
             to_binary(&query_simulate_unbond(deps, env, dregen_amount)?)
         }
+        QueryMsg::DiversityConstraints {} => to_binary(&query_diversity_constraints(deps)?),
+        QueryMsg::Decentralization {} => to_binary(&query_decentralization(deps)?),
+        QueryMsg::ValidatorCache {} => to_binary(&query_validator_cache(deps)?),
+        QueryMsg::RateHistory { start, end, limit } => {
+            to_binary(&query_rate_history(deps, start, end, limit)?)
+        }
+        QueryMsg::TwapRate { start, end } => to_binary(&query_twap_rate(deps, env, start, end)?),
+        QueryMsg::Hooks {} => to_binary(&query_hooks(deps)?),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::marker::PhantomData;
+
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        coin, coins, from_json, to_json_binary, Addr, Coin, ContractResult, CosmosMsg,
+        DistributionMsg, Empty, FullDelegation, OwnedDeps, Querier, QuerierResult, QueryRequest,
+        ReplyOn, Response, StakingMsg, StakingQuery, Storage, SubMsgResponse, SubMsgResult,
+        SystemResult, Validator, ValidatorResponse as ChainValidatorResponse, WasmMsg, WasmQuery,
+    };
+    use cw20::Cw20ExecuteMsg;
+    use regen_types::{
+        ActiveValidatorsResponse, DelegationAmount, RegistryExecuteMsg, ValidatorTag,
+    };
+
+    use crate::msg::ValidatorParams;
+    use crate::state::{
+        DiversityConstraint, ACTIVE_VALIDATORS, DIVERSITY_CONSTRAINTS, PENDING_REGISTRY_SET,
+        STATE, VALIDATORS,
+    };
+
+    const ADMIN: &str = "admin";
+    const REGISTRY: &str = "registry";
+    const VAL1: &str = "regenvaloper1a";
+    const VAL2: &str = "regenvaloper1b";
+
+    fn chain_validator(address: &str) -> Validator {
+        Validator {
+            address: address.to_string(),
+            commission: Decimal::percent(5),
+            max_commission: Decimal::percent(20),
+            max_change_rate: Decimal::percent(1),
+        }
+    }
+
+    fn params(weights: &[(&str, u64)]) -> Vec<ValidatorParams> {
+        weights
+            .iter()
+            .map(|(address, pct)| ValidatorParams {
+                address: address.to_string(),
+                weight: Decimal::percent(*pct),
+            })
+            .collect()
+    }
+
+    fn instantiate_msg(registry: Option<&str>) -> InstantiateMsg {
+        InstantiateMsg {
+            admin: ADMIN.to_string(),
+            fee_rate: Decimal::zero(),
+            unbonding_period: 1_814_400,
+            max_validators: 10,
+            min_delegation: Uint128::new(100),
+            dregen_token: Some("dregen".to_string()),
+            validators: params(&[(VAL1, 50), (VAL2, 50)]),
+            validator_registry: registry.map(str::to_string),
+            rewards_contract: None,
+            drain_batch: None,
+            delegation_strategy: None,
+            stake_routing: None,
+            max_unbond_validators: None,
+            slashing_dust: None,
+        }
+    }
+
+    fn setup(registry: Option<&str>) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_staking("uregen", &[chain_validator(VAL1), chain_validator(VAL2)], &[]);
+        let msg = instantiate_msg(registry);
+        instantiate(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        deps
+    }
+
+    /// Staking module that still answers `Validator` queries for `jailed` after it has left the
+    /// bonded set, as the chain does for jailed and unbonded validators
+    struct JailedQuerier {
+        base: MockQuerier,
+        jailed: Validator,
+    }
+
+    impl Querier for JailedQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_json(bin_request) {
+                Ok(QueryRequest::<Empty>::Staking(StakingQuery::Validator { address }))
+                    if address == self.jailed.address =>
+                {
+                    let res = ChainValidatorResponse {
+                        validator: Some(self.jailed.clone()),
+                    };
+                    SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    /// `deps` with only `bonded` in the bonded set and `jailed` still known to the chain
+    fn jail(
+        deps: OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        jailed: &str,
+        bonded: &[&str],
+    ) -> OwnedDeps<MockStorage, MockApi, JailedQuerier> {
+        let mut base = deps.querier;
+        let bonded: Vec<Validator> = bonded.iter().map(|v| chain_validator(v)).collect();
+        base.update_staking("uregen", &bonded, &[]);
+        let deps = OwnedDeps {
+            storage: deps.storage,
+            api: deps.api,
+            querier: JailedQuerier {
+                base,
+                jailed: chain_validator(jailed),
+            },
+            custom_query_type: PhantomData,
+        };
+        assert!(deps.as_ref().querier.query_validator(jailed).unwrap().is_some());
+        deps
     }
-}
\ No newline at end of file
+
+    /// Serve `weights` as this hub's view of the registry's active set
+    fn mock_registry(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        weights: &[(&str, u64)],
+    ) {
+        let validators = params(weights);
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == REGISTRY => {
+                let res = ActiveValidatorsResponse {
+                    validators: validators.clone(),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+            }
+            _ => panic!("unexpected query"),
+        });
+    }
+
+    /// Set the hub's delegations as the staking module reports them
+    fn mock_delegations(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        delegations: &[(&str, u128)],
+    ) {
+        let delegations: Vec<FullDelegation> = delegations
+            .iter()
+            .map(|(validator, amount)| FullDelegation {
+                delegator: Addr::unchecked(mock_env().contract.address),
+                validator: validator.to_string(),
+                amount: coin(*amount, "uregen"),
+                can_redelegate: coin(*amount, "uregen"),
+                accumulated_rewards: vec![],
+            })
+            .collect();
+        deps.querier.update_staking(
+            "uregen",
+            &[chain_validator(VAL1), chain_validator(VAL2)],
+            &delegations,
+        );
+    }
+
+    /// Delegations in the report `res` sends to the registry
+    fn reported(res: &Response) -> Vec<DelegationAmount> {
+        res.messages
+            .iter()
+            .find_map(|m| match &m.msg {
+                CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. })
+                    if contract_addr == REGISTRY =>
+                {
+                    assert_eq!((m.id, m.reply_on.clone()), (REGISTRY_REPLY_ID, ReplyOn::Error));
+                    match from_json(msg).unwrap() {
+                        RegistryExecuteMsg::DelegationReport { delegations } => Some(delegations),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .expect("no delegation report")
+    }
+
+    fn delegated(deps: Deps, validator: &str) -> Uint128 {
+        VALIDATORS.load(deps.storage, validator.to_string()).unwrap().delegated_amount
+    }
+
+    #[test]
+    fn registry_update_is_stored_and_applied_by_a_crank() {
+        let mut deps = setup(Some(REGISTRY));
+        mock_registry(&mut deps, &[(VAL1, 50), (VAL2, 50)]);
+        let stake = ExecuteMsg::Stake {};
+        execute(deps.as_mut(), mock_env(), mock_info("user", &coins(1_000, "uregen")), stake)
+            .unwrap();
+
+        let push = ExecuteMsg::RegistryUpdate {
+            validators: params(&[(VAL1, 25), (VAL2, 75)]),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), push.clone())
+            .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // The push only stores the set, so it cannot fail on redelegation
+        let res = execute(deps.as_mut(), mock_env(), mock_info(REGISTRY, &[]), push).unwrap();
+        assert!(res.messages.is_empty());
+        assert!(PENDING_REGISTRY_SET.may_load(&deps.storage).unwrap().is_some());
+        assert_eq!(delegated(deps.as_ref(), VAL1), Uint128::new(500));
+
+        mock_registry(&mut deps, &[(VAL1, 25), (VAL2, 75)]);
+        let apply = ExecuteMsg::ApplyRegistryUpdate {};
+        let res =
+            execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), apply.clone()).unwrap();
+        assert!(res.messages.iter().any(|m| matches!(
+            &m.msg,
+            CosmosMsg::Staking(StakingMsg::Redelegate { src_validator, dst_validator, .. })
+                if src_validator == VAL1 && dst_validator == VAL2
+        )));
+        assert_eq!(delegated(deps.as_ref(), VAL1), Uint128::new(250));
+        assert_eq!(delegated(deps.as_ref(), VAL2), Uint128::new(750));
+
+        // Nothing left to apply
+        execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), apply).unwrap_err();
+    }
+
+    #[test]
+    fn delegation_reports_list_only_touched_validators() {
+        let mut deps = setup(Some(REGISTRY));
+        mock_registry(&mut deps, &[(VAL1, 50), (VAL2, 50)]);
+        let stake = ExecuteMsg::Stake {};
+        let res =
+            execute(deps.as_mut(), mock_env(), mock_info("user", &coins(1_000, "uregen")), stake)
+                .unwrap();
+        assert_eq!(reported(&res).len(), 2);
+
+        mock_delegations(&mut deps, &[(VAL1, 400), (VAL2, 500)]);
+        let reconcile = ExecuteMsg::ReconcileSlashing {};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), reconcile).unwrap();
+        assert_eq!(
+            reported(&res),
+            vec![DelegationAmount {
+                validator: VAL1.to_string(),
+                amount: Uint128::new(400),
+            }]
+        );
+
+        // A registry that rejects the report is rolled back on its own
+        let failed = Reply {
+            id: REGISTRY_REPLY_ID,
+            result: SubMsgResult::Err("registry paused".to_string()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), failed).unwrap();
+        assert_eq!(res.attributes[0].value, "registry_notification_failed");
+        assert_eq!(res.attributes[1].value, "registry paused");
+    }
+
+    #[test]
+    fn rounding_shortfalls_are_not_slashing() {
+        let mut deps = setup(None);
+        let stake = ExecuteMsg::Stake {};
+        execute(deps.as_mut(), mock_env(), mock_info("user", &coins(1_000, "uregen")), stake)
+            .unwrap();
+        let slashes = |deps: Deps| {
+            VALIDATORS.load(deps.storage, VAL1.to_string()).unwrap().slashing_events
+        };
+
+        // One uregen lost to share-to-token truncation is written off without a slashing event
+        mock_delegations(&mut deps, &[(VAL1, 499), (VAL2, 500)]);
+        let reconcile = ExecuteMsg::ReconcileSlashing {};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), reconcile.clone())
+            .unwrap();
+        assert!(res.events.is_empty());
+        assert_eq!(slashes(deps.as_ref()), 0);
+        assert_eq!(delegated(deps.as_ref(), VAL1), Uint128::new(499));
+
+        mock_delegations(&mut deps, &[(VAL1, 300), (VAL2, 500)]);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), reconcile).unwrap();
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(slashes(deps.as_ref()), 1);
+    }
+
+    #[test]
+    fn tokenized_shares_mint_the_redeemed_delegation() {
+        let mut deps = setup(None);
+        let stake_shares =
+            |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, funds: &[Coin]| {
+                let info = mock_info("staker", funds);
+                execute(deps.as_mut(), mock_env(), info, ExecuteMsg::StakeTokenizedShares {})
+            };
+
+        let err = stake_shares(&mut deps, &[coin(500, "uregen")]).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidTokenizedShares { .. }));
+        let err = stake_shares(&mut deps, &[coin(500, "regenvaloper1z/7")]).unwrap_err();
+        assert!(matches!(err, ContractError::ValidatorNotWhitelisted { .. }));
+        let err = stake_shares(&mut deps, &[coin(50, "regenvaloper1a/7")]).unwrap_err();
+        assert!(matches!(err, ContractError::InsufficientStake { .. }));
+        stake_shares(&mut deps, &[coin(500, "regenvaloper1a/7"), coin(500, "regenvaloper1b/8")])
+            .unwrap_err();
+
+        let res = stake_shares(&mut deps, &[coin(500, "regenvaloper1a/7")]).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, REDEEM_SHARES_REPLY_ID);
+        assert_eq!(res.messages[0].reply_on, ReplyOn::Success);
+        assert!(matches!(res.messages[0].msg, CosmosMsg::Stargate { .. }));
+
+        let redeemed = Reply {
+            id: REDEEM_SHARES_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        };
+        // A delegation that grew by more than the redeemed shares is not credited to the staker
+        mock_delegations(&mut deps, &[(VAL1, 600)]);
+        reply(deps.as_mut(), mock_env(), redeemed.clone()).unwrap_err();
+        mock_delegations(&mut deps, &[(VAL1, 0)]);
+        stake_shares(&mut deps, &[coin(500, "regenvaloper1a/7")]).unwrap();
+
+        // The shares were slashed before tokenization and redeem for less than one token each
+        mock_delegations(&mut deps, &[(VAL1, 480)]);
+        let res = reply(deps.as_mut(), mock_env(), redeemed.clone()).unwrap();
+        let CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) = &res.messages[0].msg
+        else {
+            panic!("expected a mint");
+        };
+        assert_eq!(contract_addr, "dregen");
+        assert_eq!(
+            from_json::<Cw20ExecuteMsg>(msg).unwrap(),
+            Cw20ExecuteMsg::Mint {
+                recipient: "staker".to_string(),
+                amount: Uint128::new(480),
+            }
+        );
+        assert_eq!(delegated(deps.as_ref(), VAL1), Uint128::new(480));
+        let state = STATE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.total_regen_staked, Uint128::new(480));
+        assert_eq!(state.total_dregen_supply, Uint128::new(480));
+
+        // A reply with no redemption pending must not mint again
+        reply(deps.as_mut(), mock_env(), redeemed).unwrap_err();
+        let unknown = Reply {
+            id: 99,
+            result: SubMsgResult::Err("failed".to_string()),
+        };
+        let err = reply(deps.as_mut(), mock_env(), unknown).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownReplyId { id: 99 }));
+    }
+
+    #[test]
+    fn validators_outside_the_bonded_set_are_rejected() {
+        let mut deps = jail(setup(None), "regenvaloper1c", &[VAL1, VAL2]);
+        let update = ExecuteMsg::UpdateValidators {
+            validators: params(&[(VAL1, 50), ("regenvaloper1c", 50)]),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidValidator { .. }));
+    }
+
+    #[test]
+    fn sync_deactivates_jailed_validators() {
+        let mut deps = jail(setup(None), VAL2, &[VAL1]);
+        let sync = ExecuteMsg::SyncValidators {};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), sync).unwrap();
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(res.events[0].ty, "validator_deactivated");
+        assert!(VALIDATORS.load(deps.as_ref().storage, VAL1.to_string()).unwrap().is_active);
+        assert!(!VALIDATORS.load(deps.as_ref().storage, VAL2.to_string()).unwrap().is_active);
+    }
+
+    /// Hub with 500 uregen on each validator after dropping `VAL2` from the set, so it drains.
+    /// The chain blocks redelegating from `VAL2`, so draining undelegates.
+    fn draining_setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = setup(None);
+        let stake = ExecuteMsg::Stake {};
+        execute(deps.as_mut(), mock_env(), mock_info("user", &coins(1_000, "uregen")), stake)
+            .unwrap();
+        let delegations: Vec<FullDelegation> = [(VAL1, 500), (VAL2, 0)]
+            .iter()
+            .map(|(validator, can_redelegate)| FullDelegation {
+                delegator: mock_env().contract.address,
+                validator: validator.to_string(),
+                amount: coin(500, "uregen"),
+                can_redelegate: coin(*can_redelegate, "uregen"),
+                accumulated_rewards: coins(7, "uregen"),
+            })
+            .collect();
+        deps.querier.update_staking(
+            "uregen",
+            &[chain_validator(VAL1), chain_validator(VAL2)],
+            &delegations,
+        );
+        let update = ExecuteMsg::UpdateValidators {
+            validators: params(&[(VAL1, 100)]),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+        assert!(VALIDATORS.load(deps.as_ref().storage, VAL2.to_string()).unwrap().draining);
+        deps
+    }
+
+    #[test]
+    fn rewards_are_harvested_from_draining_validators() {
+        let mut deps = draining_setup();
+        let claim = ExecuteMsg::ClaimRewards {};
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), claim).unwrap();
+        let harvested: Vec<&str> = res
+            .messages
+            .iter()
+            .filter_map(|m| match &m.msg {
+                CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward { validator }) => {
+                    Some(validator.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(harvested, vec![VAL1, VAL2]);
+        assert_eq!(res.attributes[2].value, "14");
+    }
+
+    #[test]
+    fn drained_stake_is_restaked_only_from_unowed_balance() {
+        let mut deps = draining_setup();
+        let drain = ExecuteMsg::DrainValidators {};
+        execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), drain.clone()).unwrap();
+        assert!(!VALIDATORS.has(deps.as_ref().storage, VAL2.to_string()));
+
+        // 1,000 of the hub's balance is owed to unbonding users
+        let mut state = STATE.load(deps.as_ref().storage).unwrap();
+        state.pending_unbonding = Uint128::new(1_000);
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(1_814_400);
+        let contract = env.contract.address.to_string();
+
+        // The chain's unbonding has not returned all funds yet
+        deps.querier.update_balance(&contract, coins(1_200, "uregen"));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), drain.clone())
+            .unwrap();
+        assert_eq!(res.attributes[1].value, "200");
+
+        // Never more than was undelegated, even with more balance on hand
+        deps.querier.update_balance(&contract, coins(5_000, "uregen"));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), drain.clone())
+            .unwrap();
+        assert_eq!(res.attributes[1].value, "300");
+        let res = execute(deps.as_mut(), env, mock_info("anyone", &[]), drain).unwrap();
+        assert_eq!(res.attributes[1].value, "0");
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        let mut deps = setup(None);
+        let msg = InstantiateMsg {
+            drain_batch: Some(Uint128::zero()),
+            ..instantiate_msg(None)
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert_eq!(err.to_string(), "drain_batch must be greater than zero");
+        let msg = InstantiateMsg {
+            max_unbond_validators: Some(0),
+            ..instantiate_msg(None)
+        };
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert_eq!(err.to_string(), "max_unbond_validators must be greater than zero");
+
+        let update = |drain_batch: Option<u128>, max_unbond_validators: Option<u32>| {
+            ExecuteMsg::UpdateConfig {
+                admin: None,
+                fee_rate: None,
+                max_validators: None,
+                dregen_token: None,
+                validator_registry: None,
+                rewards_contract: None,
+                drain_batch: drain_batch.map(Uint128::new),
+                delegation_strategy: None,
+                stake_routing: None,
+                max_unbond_validators,
+                slashing_dust: None,
+            }
+        };
+        for msg in [update(Some(0), None), update(None, Some(0))] {
+            let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+            assert!(matches!(err, ContractError::ZeroLimit { .. }));
+        }
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update(Some(1), Some(1)))
+            .unwrap();
+        let config = CONFIG.load(deps.as_ref().storage).unwrap();
+        assert_eq!(config.drain_batch, Some(Uint128::one()));
+        assert_eq!(config.max_unbond_validators, Some(1));
+    }
+
+    #[test]
+    fn migrate_rebuilds_a_missing_validator_cache() {
+        // State written by a release that predates the cache
+        let predates_cache = || {
+            let mut deps = setup(None);
+            VALIDATOR_TOTALS.remove(deps.as_mut().storage);
+            deps
+        };
+        let update = ExecuteMsg::UpdateValidators {
+            validators: params(&[(VAL1, 100)]),
+        };
+
+        let mut deps = predates_cache();
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update.clone())
+            .unwrap_err();
+        assert!(err.to_string().contains("Validator cache out of sync"));
+
+        let mut deps = predates_cache();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[2].value, "2");
+        let totals = VALIDATOR_TOTALS.load(deps.as_ref().storage).unwrap();
+        assert_eq!(totals.active_count, 2);
+        assert_eq!(totals.active_weight, Decimal::one());
+
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update).unwrap();
+        let totals = VALIDATOR_TOTALS.load(deps.as_ref().storage).unwrap();
+        assert_eq!(totals.active_count, 1);
+    }
+
+    #[test]
+    fn migrate_reads_validators_stored_by_earlier_releases() {
+        let mut deps = setup(None);
+        // Validator entries as stored before draining and tags existed, with no cache at all
+        for address in [VAL1, VAL2] {
+            let old = format!(
+                r#"{{"address":"{address}","delegated_amount":"300","weight":"0.5",
+                "last_reward_claim":"0","slashing_events":0,"uptime_percentage":"1",
+                "commission_rate":"0.1","is_active":true}}"#
+            );
+            deps.storage.set(&VALIDATORS.key(address.to_string()), old.as_bytes());
+            ACTIVE_VALIDATORS.remove(deps.as_mut().storage, address.to_string());
+        }
+        VALIDATOR_TOTALS.remove(deps.as_mut().storage);
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        let totals = VALIDATOR_TOTALS.load(deps.as_ref().storage).unwrap();
+        assert_eq!(totals.active_count, 2);
+        assert_eq!(totals.active_weight, Decimal::one());
+        assert_eq!(totals.total_delegated, Uint128::new(600));
+        let v = VALIDATORS.load(deps.as_ref().storage, VAL1.to_string()).unwrap();
+        assert!(!v.draining);
+        assert!(v.tags.is_empty());
+    }
+
+    #[test]
+    fn invalid_diversity_constraints_are_rejected() {
+        let mut deps = setup(None);
+        let constraint = |value: Option<&str>, max: Option<u64>, min: Option<u64>| {
+            DiversityConstraint {
+                key: "region".to_string(),
+                value: value.map(str::to_string),
+                max_share: max.map(Decimal::percent),
+                min_share: min.map(Decimal::percent),
+            }
+        };
+        let update = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+                      sender: &str,
+                      constraints: Vec<DiversityConstraint>| {
+            let msg = ExecuteMsg::UpdateDiversityConstraints { constraints };
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+        };
+
+        let valid = vec![constraint(None, Some(40), None), constraint(Some("eu"), None, Some(20))];
+        let err = update(&mut deps, "anyone", valid.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        update(&mut deps, ADMIN, valid.clone()).unwrap();
+
+        let empty_key = DiversityConstraint {
+            key: String::new(),
+            ..constraint(None, Some(40), None)
+        };
+        for invalid in [
+            vec![empty_key],
+            vec![constraint(None, Some(101), None)],
+            vec![constraint(Some("eu"), Some(30), Some(40))],
+            vec![constraint(Some("eu"), None, Some(60)), constraint(Some("us"), None, Some(50))],
+        ] {
+            update(&mut deps, ADMIN, invalid).unwrap_err();
+        }
+        assert_eq!(DIVERSITY_CONSTRAINTS.load(deps.as_ref().storage).unwrap(), valid);
+
+        let tag = |key: &str, value: &str| ValidatorTag {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let set_tags = |tags: Vec<ValidatorTag>| ExecuteMsg::SetValidatorTags {
+            address: VAL1.to_string(),
+            tags,
+        };
+        let msg = set_tags(vec![tag("region", "eu")]);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let msg = set_tags(vec![tag("region", "")]);
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        let msg = ExecuteMsg::SetValidatorTags {
+            address: "regenvaloper1z".to_string(),
+            tags: vec![tag("region", "eu")],
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::ValidatorNotFound { .. }));
+        assert!(VALIDATORS.load(deps.as_ref().storage, VAL1.to_string()).unwrap().tags.is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::{OverflowError, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,12 +6,18 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
     #[error("Invalid fee rate")]
     InvalidFeeRate {},
 
+    #[error("{field} must be greater than zero")]
+    ZeroLimit { field: String },
+
     #[error("Contract is paused")]
     ContractPaused {},
 
@@ -24,6 +30,21 @@ pub enum ContractError {
     #[error("Validator not found: {validator}")]
     ValidatorNotFound { validator: String },
 
+    #[error("Invalid validator {address}: not found in the bonded validator set")]
+    InvalidValidator { address: String },
+
+    #[error("Invalid tokenized shares: {denom}")]
+    InvalidTokenizedShares { denom: String },
+
+    #[error("Validator not whitelisted: {validator}")]
+    ValidatorNotWhitelisted { validator: String },
+
+    #[error("Validator set is managed by the validator registry")]
+    ValidatorSetManagedByRegistry {},
+
+    #[error("Unknown reply id: {id}")]
+    UnknownReplyId { id: u64 },
+
     #[error("Invalid unbond amount")]
     InvalidUnbondAmount {},
 
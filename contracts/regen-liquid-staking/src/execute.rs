@@ -1,20 +1,39 @@
+use std::collections::BTreeSet;
+
 use cosmwasm_std::{
-    to_binary, Coin, CosmosMsg, Deps, DepsMut, Decimal, Env, MessageInfo, Order, Response,
-    Uint128,
+    to_binary, Coin, CosmosMsg, Deps, DepsMut, Decimal, Env, Event, MessageInfo, Order, Reply,
+    Response, StdResult, Storage, SubMsg, Timestamp, Uint128,
 };
 use cosmwasm_std::{BankMsg, DistributionMsg, StakingMsg, WasmMsg};
 use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::Bound;
+use regen_types::{
+    validate_weights, ActiveValidatorsResponse, DelegationAmount, RegistryExecuteMsg,
+    RegistryQueryMsg, RewardsExecuteMsg, StakingHookEvent, ValidatorTag, VotingPowerResponse,
+};
 
 use crate::error::ContractError;
+use crate::helpers::{bonded_commissions, ensure_admin, ensure_not_paused, validate_validator};
+use crate::hooks::{hook_msgs, MAX_HOOKS};
+use crate::lsm::{parse_tokenized_share_denom, redeem_tokens_for_shares_msg};
 use crate::math::{
-    calculate_dregen_mint_amount, calculate_exchange_rate, calculate_fee,
-    calculate_regen_return_amount, calculate_validator_distribution,
+    apply_decentralization, apply_diversity_constraints, calculate_dregen_mint_amount,
+    calculate_exchange_rate, calculate_fee, calculate_rebalance_moves,
+    calculate_regen_return_amount,
 };
 use crate::msg::ValidatorParams;
 use crate::state::{
-    Config, State, UnbondingRequest, ValidatorInfo, CONFIG, NEXT_UNBONDING_ID, STATE,
-    UNBONDING_REQUESTS, VALIDATORS,
+    rebuild_validator_cache, remove_validator, save_state, save_validator, Config,
+    DecentralizationParams, DelegationStrategyKind, DiversityConstraint, PendingShareRedemption,
+    StakeRouting, State, UnbondingRequest, ValidatorInfo, ACTIVE_VALIDATORS, CONFIG,
+    DECENTRALIZATION, DEFAULT_SLASHING_DUST, DIVERSITY_CONSTRAINTS, HOOKS, NEXT_UNBONDING_ID,
+    PENDING_REGISTRY_SET, PENDING_SHARE_REDEMPTION, RESTAKE_QUEUE, STATE, UNBONDING_REQUESTS,
+    VALIDATORS, VALIDATOR_TOTALS,
 };
+use crate::strategy::{performance_score, route_stake, route_unbond, strategy_for, Candidate};
+
+pub const REDEEM_SHARES_REPLY_ID: u64 = 1;
+pub const REGISTRY_REPLY_ID: u64 = 3;
 
 pub fn execute_stake(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
@@ -57,9 +76,9 @@ pub fn execute_stake(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respo
         calculate_dregen_mint_amount(net_stake_amount, current_exchange_rate)?;
 
     // Get active validators and calculate distribution
-    let active_validators = get_active_validators(deps.as_ref())?;
+    let active_validators = get_target_weights(deps.as_ref(), &env)?;
     let validator_distribution =
-        calculate_validator_distribution(net_stake_amount, &active_validators)?;
+        distribute_new_stake(deps.as_ref(), net_stake_amount, &active_validators)?;
 
     // Create delegation messages
     let mut messages: Vec<CosmosMsg> = Vec::new();
@@ -74,17 +93,7 @@ pub fn execute_stake(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respo
         }));
 
         // Update validator info
-        VALIDATORS.update(
-            deps.storage,
-            validator_addr.clone(),
-            |validator_info: Option<ValidatorInfo>| match validator_info {
-                Some(mut info) => {
-                    info.delegated_amount = info.delegated_amount.checked_add(delegation_amount).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?;
-                    Ok(info)
-                }
-                None => Err(ContractError::ValidatorNotFound { validator: validator_addr }),
-            },
-        )?;
+        add_delegation(deps.storage, &config, &validator_addr, delegation_amount, env.block.time)?;
     }
 
     // Mint dREGEN tokens to user
@@ -114,10 +123,22 @@ pub fn execute_stake(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respo
     state.exchange_rate = current_exchange_rate;
     state.last_update_time = env.block.time;
 
-    STATE.save(deps.storage, &state)?;
+    save_state(deps.storage, &state, env.block.time)?;
+    let report = delegation_report_msg(deps.storage, &config, touched_validators(&messages))?;
+    let hooks = hook_msgs(
+        deps.storage,
+        &state,
+        StakingHookEvent::Stake {
+            user: info.sender.to_string(),
+            regen_amount,
+            dregen_amount: dregen_mint_amount,
+        },
+    )?;
 
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(report)
+        .add_submessages(hooks)
         .add_attribute("method", "stake")
         .add_attribute("staker", info.sender)
         .add_attribute("regen_amount", regen_amount)
@@ -126,6 +147,143 @@ pub fn execute_stake(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Respo
         .add_attribute("fee_amount", fee_amount))
 }
 
+pub fn execute_stake_tokenized_shares(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_not_paused(&config)?;
+
+    let shares = match info.funds.as_slice() {
+        [coin] => coin.clone(),
+        _ => {
+            return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+                "Expected exactly one tokenized share coin",
+            )))
+        }
+    };
+    let validator = parse_tokenized_share_denom(&shares.denom).ok_or_else(|| {
+        ContractError::InvalidTokenizedShares {
+            denom: shares.denom.clone(),
+        }
+    })?;
+
+    match VALIDATORS.may_load(deps.storage, validator.clone())? {
+        Some(v) if v.is_active => {}
+        _ => return Err(ContractError::ValidatorNotWhitelisted { validator }),
+    }
+
+    if shares.amount < config.min_delegation {
+        return Err(ContractError::InsufficientStake {
+            minimum: config.min_delegation,
+            received: shares.amount,
+        });
+    }
+
+    // Shares may be worth less than one token each if the validator was slashed,
+    // so the minted amount is derived from the actual delegation increase in the reply.
+    let delegation_before = deps
+        .querier
+        .query_delegation(&env.contract.address, &validator)?
+        .map(|d| d.amount.amount)
+        .unwrap_or_default();
+
+    PENDING_SHARE_REDEMPTION.save(
+        deps.storage,
+        &PendingShareRedemption {
+            staker: info.sender.clone(),
+            validator: validator.clone(),
+            shares: shares.amount,
+            delegation_before,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            redeem_tokens_for_shares_msg(env.contract.address.as_str(), &shares),
+            REDEEM_SHARES_REPLY_ID,
+        ))
+        .add_attribute("method", "stake_tokenized_shares")
+        .add_attribute("staker", info.sender)
+        .add_attribute("validator", validator)
+        .add_attribute("shares", shares.amount))
+}
+
+pub fn handle_redeem_shares_reply(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    let pending = PENDING_SHARE_REDEMPTION.load(deps.storage)?;
+    PENDING_SHARE_REDEMPTION.remove(deps.storage);
+
+    let delegation_after = deps
+        .querier
+        .query_delegation(&env.contract.address, &pending.validator)?
+        .map(|d| d.amount.amount)
+        .unwrap_or_default();
+    let regen_amount = delegation_after.checked_sub(pending.delegation_before)?;
+    // Each share redeems for at most one token; more means the delegation moved for another reason
+    if regen_amount > pending.shares {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Redeemed delegation exceeds the redeemed shares",
+        )));
+    }
+
+    let total_rewards = query_total_rewards(deps.as_ref(), &env)?;
+    let current_exchange_rate = calculate_exchange_rate(
+        state.total_regen_staked,
+        state.total_dregen_supply,
+        total_rewards,
+    )?;
+    let dregen_mint_amount = calculate_dregen_mint_amount(regen_amount, current_exchange_rate)?;
+
+    let mut validator = VALIDATORS
+        .may_load(deps.storage, pending.validator.clone())?
+        .ok_or_else(|| ContractError::ValidatorNotFound {
+            validator: pending.validator.clone(),
+        })?;
+    validator.delegated_amount = validator.delegated_amount.checked_add(regen_amount)?;
+    save_validator(deps.storage, &validator)?;
+
+    state.total_regen_staked = state.total_regen_staked.checked_add(regen_amount)?;
+    state.total_dregen_supply = state.total_dregen_supply.checked_add(dregen_mint_amount)?;
+    state.exchange_rate = current_exchange_rate;
+    state.last_update_time = env.block.time;
+    save_state(deps.storage, &state, env.block.time)?;
+
+    let mint = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: config.dregen_token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Mint {
+            recipient: pending.staker.to_string(),
+            amount: dregen_mint_amount,
+        })?,
+        funds: vec![],
+    });
+
+    let hooks = hook_msgs(
+        deps.storage,
+        &state,
+        StakingHookEvent::Stake {
+            user: pending.staker.to_string(),
+            regen_amount,
+            dregen_amount: dregen_mint_amount,
+        },
+    )?;
+
+    let report = delegation_report_msg(deps.storage, &config, vec![pending.validator.clone()])?;
+
+    Ok(Response::new()
+        .add_message(mint)
+        .add_submessages(report)
+        .add_submessages(hooks)
+        .add_attribute("method", "redeem_tokenized_shares")
+        .add_attribute("staker", pending.staker)
+        .add_attribute("validator", pending.validator)
+        .add_attribute("regen_amount", regen_amount)
+        .add_attribute("dregen_amount", dregen_mint_amount)
+        .add_attribute("exchange_rate", current_exchange_rate.to_string()))
+}
+
 pub fn execute_unbond(
     deps: DepsMut,
     env: Env,
@@ -160,10 +318,39 @@ pub fn execute_unbond(
     let fee_amount = calculate_fee(regen_amount, config.fee_rate)?;
     let net_unbond_amount = regen_amount.checked_sub(fee_amount).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?;
 
-    // Get validators and calculate undelegation distribution
-    let active_validators = get_active_validators_with_delegations(deps.as_ref())?;
-    let undelegation_distribution =
-        calculate_validator_distribution(net_unbond_amount, &active_validators)?;
+    // Undelegate from inactive and overweight validators first
+    let targets = get_target_weights(deps.as_ref(), &env)?;
+    let candidates = stake_candidates(deps.as_ref(), &config, &targets)?;
+    // Only look for stake outside the active set when the cached total says there is some
+    let on_targets: Uint128 = candidates.iter().map(|c| c.delegated).sum();
+    let total_delegated = VALIDATOR_TOTALS
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        .total_delegated;
+    let inactive: Vec<(String, Uint128)> = if total_delegated > on_targets {
+        VALIDATORS
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter_map(|item| match item {
+                Ok((addr, info))
+                    if !info.delegated_amount.is_zero()
+                        && !targets.iter().any(|(a, _)| a == &addr) =>
+                {
+                    Some(Ok((addr, info.delegated_amount)))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(ContractError::from(e))),
+            })
+            .collect::<Result<_, _>>()?
+    } else {
+        vec![]
+    };
+    let undelegation_distribution = route_unbond(
+        strategy_for(config.delegation_strategy),
+        net_unbond_amount,
+        &candidates,
+        &inactive,
+        config.max_unbond_validators,
+    )?;
 
     // Create undelegation messages
     let mut messages: Vec<CosmosMsg> = Vec::new();
@@ -178,17 +365,7 @@ pub fn execute_unbond(
         }));
 
         // Update validator info
-        VALIDATORS.update(
-            deps.storage,
-            validator_addr.clone(),
-            |validator_info: Option<ValidatorInfo>| match validator_info {
-                Some(mut info) => {
-                    info.delegated_amount = info.delegated_amount.checked_sub(undelegation_amount).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?;
-                    Ok(info)
-                }
-                None => Err(ContractError::ValidatorNotFound { validator: validator_addr }),
-            },
-        )?;
+        remove_delegation(deps.storage, &validator_addr, undelegation_amount)?;
     }
 
     // Burn dREGEN tokens from user
@@ -222,10 +399,22 @@ pub fn execute_unbond(
     state.pending_unbonding = state.pending_unbonding.checked_add(net_unbond_amount).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?;
     state.exchange_rate = current_exchange_rate;
     state.last_update_time = env.block.time;
-    STATE.save(deps.storage, &state)?;
+    save_state(deps.storage, &state, env.block.time)?;
+    let report = delegation_report_msg(deps.storage, &config, touched_validators(&messages))?;
+    let hooks = hook_msgs(
+        deps.storage,
+        &state,
+        StakingHookEvent::Unbond {
+            user: info.sender.to_string(),
+            dregen_amount,
+            regen_amount: net_unbond_amount,
+        },
+    )?;
 
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(report)
+        .add_submessages(hooks)
         .add_attribute("method", "unbond")
         .add_attribute("user", info.sender)
         .add_attribute("dregen_amount", dregen_amount)
@@ -269,20 +458,32 @@ pub fn execute_claim_unbonding(
     state.pending_unbonding = state
         .pending_unbonding
         .checked_sub(unbonding_request.regen_amount).map_err(|e| cosmwasm_std::StdError::generic_err(e.to_string()))?;
-    STATE.save(deps.storage, &state)?;
+    save_state(deps.storage, &state, env.block.time)?;
 
     // Remove unbonding request
     UNBONDING_REQUESTS.remove(deps.storage, unbonding_id);
 
+    let mut messages = vec![message];
+    if let Some(rewards_contract) = CONFIG.load(deps.storage)?.rewards_contract {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: rewards_contract.to_string(),
+            msg: to_binary(&RewardsExecuteMsg::RecordClaim {
+                user: info.sender.to_string(),
+                amount: unbonding_request.regen_amount,
+            })?,
+            funds: vec![],
+        }));
+    }
+
     Ok(Response::new()
-        .add_message(message)
+        .add_messages(messages)
         .add_attribute("method", "claim_unbonding")
         .add_attribute("user", info.sender)
         .add_attribute("unbonding_id", unbonding_id.to_string())
         .add_attribute("regen_amount", unbonding_request.regen_amount))
 }
 
-pub fn execute_claim_rewards(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn execute_claim_rewards(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
 
     // Only admin or automated process can claim rewards
@@ -290,38 +491,380 @@ pub fn execute_claim_rewards(deps: DepsMut, _env: Env, info: MessageInfo) -> Res
         return Err(ContractError::Unauthorized {});
     }
 
-    let active_validators = get_active_validators(deps.as_ref())?;
+    // Draining validators keep earning rewards until their stake has moved
+    let mut validators: BTreeSet<String> = get_active_validators(deps.as_ref(), &env)?
+        .into_iter()
+        .map(|(address, _)| address)
+        .collect();
+    for item in VALIDATORS.range(deps.storage, None, None, Order::Ascending) {
+        let (address, v) = item?;
+        if v.draining {
+            validators.insert(address);
+        }
+    }
     let mut messages: Vec<CosmosMsg> = Vec::new();
+    let mut total_harvested = Uint128::zero();
 
     // Claim rewards from all validators
-    for (validator_addr, _) in active_validators {
+    for validator_addr in validators {
+        let harvested = deps
+            .querier
+            .query_delegation(&env.contract.address, &validator_addr)?
+            .and_then(|d| {
+                d.accumulated_rewards
+                    .into_iter()
+                    .find(|c| c.denom == "uregen")
+            })
+            .map(|c| c.amount)
+            .unwrap_or_default();
+
         messages.push(CosmosMsg::Distribution(
             DistributionMsg::WithdrawDelegatorReward {
                 validator: validator_addr.clone(),
             },
         ));
+
+        if let Some(rewards_contract) = &config.rewards_contract {
+            if !harvested.is_zero() {
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: rewards_contract.to_string(),
+                    msg: to_binary(&RewardsExecuteMsg::RecordReward {
+                        validator: validator_addr.clone(),
+                        amount: harvested,
+                    })?,
+                    funds: vec![],
+                }));
+            }
+        }
+        total_harvested = total_harvested.checked_add(harvested)?;
     }
 
+    let mut state = STATE.load(deps.storage)?;
+    state.total_rewards_claimed = state.total_rewards_claimed.checked_add(total_harvested)?;
+    save_state(deps.storage, &state, env.block.time)?;
+    let hooks = if total_harvested.is_zero() {
+        vec![]
+    } else {
+        hook_msgs(
+            deps.storage,
+            &state,
+            StakingHookEvent::RewardsHarvested {
+                amount: total_harvested,
+            },
+        )?
+    };
+
     Ok(Response::new()
         .add_messages(messages)
+        .add_submessages(hooks)
         .add_attribute("method", "claim_rewards")
-        .add_attribute("claimer", info.sender))
+        .add_attribute("claimer", info.sender)
+        .add_attribute("harvested", total_harvested))
 }
 
-pub fn execute_rebalance(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn execute_rebalance(mut deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     // For now, restrict to admin
     let config = CONFIG.load(deps.storage)?;
     if info.sender != config.admin {
         return Err(ContractError::Unauthorized {});
     }
-    // Placeholder: rebalancing logic can be implemented to equalize weights
+    let messages = rebalance_delegations(deps.branch(), &env)?;
+    let report = delegation_report_msg(deps.storage, &config, touched_validators(&messages))?;
     Ok(Response::new()
         .add_attribute("method", "rebalance")
-        .add_attribute("status", "noop"))
+        .add_attribute("moves", messages.len().to_string())
+        .add_messages(messages)
+        .add_submessages(report))
 }
 
-pub fn execute_update_validators(
+/// Redelegate from over- to under-weight validators and record the new delegated amounts
+fn rebalance_delegations(deps: DepsMut, env: &Env) -> Result<Vec<CosmosMsg>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // Draining validators are emptied by `execute_drain_validators` instead
+    let delegations: Vec<(String, Uint128)> = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((addr, info)) if !info.draining && !info.delegated_amount.is_zero() => {
+                Some(Ok((addr, info.delegated_amount)))
+            }
+            Ok(_) => None,
+            Err(e) => Some(Err(ContractError::from(e))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let targets = get_target_weights(deps.as_ref(), env)?;
+    let moves = calculate_rebalance_moves(&delegations, &targets)?;
+
+    let mut messages: Vec<CosmosMsg> = Vec::new();
+    for (src, dst, amount) in moves {
+        messages.push(CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator: src.clone(),
+            dst_validator: dst.clone(),
+            amount: Coin {
+                denom: "uregen".to_string(),
+                amount,
+            },
+        }));
+        remove_delegation(deps.storage, &src, amount)?;
+        add_delegation(deps.storage, &config, &dst, amount, env.block.time)?;
+    }
+    Ok(messages)
+}
+
+/// Move stake off draining validators in bounded batches. Stake that cannot be redelegated
+/// yet is undelegated and re-delegated to the active set by a later crank once it matures.
+/// Validators are deleted once nothing is delegated to them.
+pub fn execute_drain_validators(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let targets = get_target_weights(deps.as_ref(), &env)?;
+    let mut messages: Vec<CosmosMsg> = Vec::new();
+    let mut notices: Vec<SubMsg> = Vec::new();
+
+    // Re-delegate undelegations from earlier cranks that have completed. Entries are due after
+    // the configured unbonding period, which can be shorter than the chain's, so only balance
+    // not owed to pending unbonding requests is used; the rest waits for a later crank.
+    let matured: Vec<(u64, Uint128)> = RESTAKE_QUEUE
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(env.block.time.seconds())),
+            Order::Ascending,
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut available = if matured.is_empty() {
+        Uint128::zero()
+    } else {
+        let balance = deps.querier.query_balance(&env.contract.address, "uregen")?.amount;
+        balance.saturating_sub(STATE.load(deps.storage)?.pending_unbonding)
+    };
+    let mut restaked = Uint128::zero();
+    for (completion, amount) in matured {
+        let restake = amount.min(available);
+        if restake.is_zero() {
+            break;
+        }
+        if restake == amount {
+            RESTAKE_QUEUE.remove(deps.storage, completion);
+        } else {
+            RESTAKE_QUEUE.save(deps.storage, completion, &(amount - restake))?;
+        }
+        available -= restake;
+        restaked = restaked.checked_add(restake)?;
+    }
+    if !restaked.is_zero() {
+        for (validator, amount) in distribute_new_stake(deps.as_ref(), restaked, &targets)? {
+            messages.push(CosmosMsg::Staking(StakingMsg::Delegate {
+                validator: validator.clone(),
+                amount: Coin {
+                    denom: "uregen".to_string(),
+                    amount,
+                },
+            }));
+            add_delegation(deps.storage, &config, &validator, amount, env.block.time)?;
+        }
+    }
+
+    let draining: Vec<ValidatorInfo> = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((_, info)) if info.draining => Some(Ok(info)),
+            Ok(_) => None,
+            Err(e) => Some(Err(ContractError::from(e))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut drained = 0u32;
+    for mut v in draining {
+        let amount = match config.drain_batch {
+            Some(batch) => batch.min(v.delegated_amount),
+            None => v.delegated_amount,
+        };
+        if !amount.is_zero() {
+            let can_redelegate = deps
+                .querier
+                .query_delegation(&env.contract.address, &v.address)?
+                .map(|d| d.can_redelegate.amount)
+                .unwrap_or_default();
+            let redelegate_amount = amount.min(can_redelegate);
+            let undelegate_amount = amount - redelegate_amount;
+
+            if !redelegate_amount.is_zero() {
+                let split = distribute_new_stake(deps.as_ref(), redelegate_amount, &targets)?;
+                for (dst, amt) in split {
+                    messages.push(CosmosMsg::Staking(StakingMsg::Redelegate {
+                        src_validator: v.address.clone(),
+                        dst_validator: dst.clone(),
+                        amount: Coin {
+                            denom: "uregen".to_string(),
+                            amount: amt,
+                        },
+                    }));
+                    add_delegation(deps.storage, &config, &dst, amt, env.block.time)?;
+                }
+            }
+            if !undelegate_amount.is_zero() {
+                messages.push(CosmosMsg::Staking(StakingMsg::Undelegate {
+                    validator: v.address.clone(),
+                    amount: Coin {
+                        denom: "uregen".to_string(),
+                        amount: undelegate_amount,
+                    },
+                }));
+                let completion = env.block.time.plus_seconds(config.unbonding_period).seconds();
+                RESTAKE_QUEUE.update(deps.storage, completion, |queued| -> Result<_, ContractError> {
+                    Ok(queued.unwrap_or_default().checked_add(undelegate_amount)?)
+                })?;
+            }
+            v.delegated_amount = v.delegated_amount.checked_sub(amount)?;
+        }
+
+        if v.delegated_amount.is_zero() {
+            remove_validator(deps.storage, &v.address)?;
+            notices.extend(validator_drained_msg(&config, &v.address)?);
+            drained += 1;
+        } else {
+            save_validator(deps.storage, &v)?;
+        }
+    }
+
+    let report = delegation_report_msg(deps.storage, &config, touched_validators(&messages))?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(report)
+        .add_submessages(notices)
+        .add_attribute("method", "drain_validators")
+        .add_attribute("restaked", restaked)
+        .add_attribute("drained", drained.to_string()))
+}
+
+/// Notify the registry, if any, that the hub no longer holds stake with `validator`
+fn validator_drained_msg(config: &Config, validator: &str) -> Result<Option<SubMsg>, ContractError> {
+    let Some(registry) = &config.validator_registry else {
+        return Ok(None);
+    };
+    Ok(Some(SubMsg::reply_on_error(
+        WasmMsg::Execute {
+            contract_addr: registry.to_string(),
+            msg: to_binary(&RegistryExecuteMsg::ValidatorDrained {
+                address: validator.to_string(),
+            })?,
+            funds: vec![],
+        },
+        REGISTRY_REPLY_ID,
+    )))
+}
+
+/// Validators whose delegations the staking messages in `messages` change
+fn touched_validators(messages: &[CosmosMsg]) -> Vec<String> {
+    messages
+        .iter()
+        .flat_map(|msg| match msg {
+            CosmosMsg::Staking(StakingMsg::Delegate { validator, .. })
+            | CosmosMsg::Staking(StakingMsg::Undelegate { validator, .. }) => {
+                vec![validator.clone()]
+            }
+            CosmosMsg::Staking(StakingMsg::Redelegate {
+                src_validator,
+                dst_validator,
+                ..
+            }) => vec![src_validator.clone(), dst_validator.clone()],
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// Report the hub's current delegations with `validators` to the registry, if any. Only the
+/// validators an operation touched are reported, keeping user operations independent of the
+/// size of the validator set; deleted validators are reported with zero. Like hooks, reports
+/// are `reply_on_error` submessages, so a failing registry never blocks the operation.
+fn delegation_report_msg(
+    storage: &dyn Storage,
+    config: &Config,
+    validators: Vec<String>,
+) -> Result<Option<SubMsg>, ContractError> {
+    let Some(registry) = &config.validator_registry else {
+        return Ok(None);
+    };
+    let validators: BTreeSet<String> = validators.into_iter().collect();
+    if validators.is_empty() {
+        return Ok(None);
+    }
+    let delegations = validators
+        .into_iter()
+        .map(|validator| {
+            let amount = VALIDATORS
+                .may_load(storage, validator.clone())?
+                .map(|v| v.delegated_amount)
+                .unwrap_or_default();
+            Ok(DelegationAmount { validator, amount })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(Some(SubMsg::reply_on_error(
+        WasmMsg::Execute {
+            contract_addr: registry.to_string(),
+            msg: to_binary(&RegistryExecuteMsg::DelegationReport { delegations })?,
+            funds: vec![],
+        },
+        REGISTRY_REPLY_ID,
+    )))
+}
+
+/// Swallow a failed registry notification, recording it for indexers
+pub fn handle_registry_reply(msg: Reply) -> Result<Response, ContractError> {
+    let error = msg.result.into_result().err().unwrap_or_default();
+    Ok(Response::new()
+        .add_attribute("method", "registry_notification_failed")
+        .add_attribute("error", error))
+}
+
+/// Store the set pushed by the registry. Applying it redelegates stake, which can fail on the
+/// chain's redelegation limits, so it is left to `execute_apply_registry_update` rather than
+/// failing the registry's transaction.
+pub fn execute_registry_update(
     deps: DepsMut,
+    info: MessageInfo,
+    validators: Vec<ValidatorParams>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.validator_registry.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    PENDING_REGISTRY_SET.save(deps.storage, &validators)?;
+    Ok(Response::new()
+        .add_attribute("method", "registry_update")
+        .add_attribute("count", validators.len().to_string()))
+}
+
+/// Apply the set last pushed by the registry and rebalance to it (permissionless)
+pub fn execute_apply_registry_update(
+    mut deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let validators = PENDING_REGISTRY_SET.may_load(deps.storage)?.ok_or_else(|| {
+        ContractError::Std(cosmwasm_std::StdError::generic_err("No registry update pending"))
+    })?;
+    PENDING_REGISTRY_SET.remove(deps.storage);
+    let count = validators.len();
+    let removed = apply_validator_set(deps.branch(), &env, &config, validators)?;
+    let messages = rebalance_delegations(deps.branch(), &env)?;
+    let report = delegation_report_msg(deps.storage, &config, touched_validators(&messages))?;
+    let drained_msgs = removed
+        .iter()
+        .map(|addr| validator_drained_msg(&config, addr))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Response::new()
+        .add_attribute("method", "apply_registry_update")
+        .add_attribute("count", count.to_string())
+        .add_attribute("moves", messages.len().to_string())
+        .add_messages(messages)
+        .add_submessages(report)
+        .add_submessages(drained_msgs.into_iter().flatten()))
+}
+
+pub fn execute_update_validators(
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     validators: Vec<ValidatorParams>,
@@ -330,53 +873,179 @@ pub fn execute_update_validators(
     if info.sender != config.admin {
         return Err(ContractError::Unauthorized {});
     }
+    if config.validator_registry.is_some() {
+        return Err(ContractError::ValidatorSetManagedByRegistry {});
+    }
+    validate_weights(&validators, config.max_validators as usize, false)?;
+    for vp in &validators {
+        validate_validator(deps.as_ref(), &vp.address)?;
+    }
+    apply_validator_set(deps.branch(), &env, &config, validators)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_validators")
+        .add_attribute("count", active_count(deps.storage)?.to_string()))
+}
+
+/// Upsert `validators` as the active set. Validators dropped from the set are deleted if
+/// nothing is delegated to them, and otherwise enter the draining state.
+/// Returns the addresses that were deleted.
+fn apply_validator_set(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    validators: Vec<ValidatorParams>,
+) -> Result<Vec<String>, ContractError> {
     if validators.len() as u32 > config.max_validators {
         return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
             "Validator set exceeds max_validators",
         )));
     }
 
-    // Mark all existing validators as inactive
     let existing: Vec<(String, ValidatorInfo)> = VALIDATORS
         .range(deps.storage, None, None, Order::Ascending)
         .map(|r| r.map_err(ContractError::from))
         .collect::<Result<Vec<_>, _>>()?;
 
+    let mut removed = Vec::new();
     for (addr, mut v) in existing {
-        v.is_active = false;
-        VALIDATORS.save(deps.storage, addr.clone(), &v)?;
+        if validators.iter().any(|vp| vp.address == addr) {
+            continue;
+        }
+        if v.delegated_amount.is_zero() {
+            remove_validator(deps.storage, &addr)?;
+            removed.push(addr);
+        } else {
+            v.is_active = false;
+            v.draining = true;
+            save_validator(deps.storage, &v)?;
+        }
     }
 
     // Upsert new validators as active with provided weights
+    let commissions = bonded_commissions(deps.as_ref())?;
     for vp in validators {
-        let _ = deps.api.addr_validate(&vp.address)?; // validate bech32
-        let info = VALIDATORS
+        let mut info = VALIDATORS
             .may_load(deps.storage, vp.address.clone())?
-            .unwrap_or(ValidatorInfo {
-                address: vp.address.clone(),
-                delegated_amount: Uint128::zero(),
-                weight: vp.weight,
-                last_reward_claim: env.block.time,
-                slashing_events: 0,
-                uptime_percentage: Decimal::percent(100),
-                commission_rate: Decimal::percent(10),
-                is_active: true,
-            });
+            .unwrap_or_else(|| new_validator_info(vp.address.clone(), vp.weight, env.block.time));
+        if let Some(commission) = commissions.get(&vp.address) {
+            info.commission_rate = *commission;
+        }
 
-        VALIDATORS.save(
+        save_validator(
             deps.storage,
-            info.address.clone(),
             &ValidatorInfo {
                 is_active: true,
+                draining: false,
                 weight: vp.weight,
                 ..info
             },
         )?;
     }
+    Ok(removed)
+}
+
+/// Refresh each validator's commission from the staking module and deactivate validators
+/// that are no longer bonded (jailed, unbonding/unbonded or unknown to the chain).
+/// Every change is emitted as a `validator_commission_changed` or `validator_deactivated` event.
+pub fn execute_sync_validators(deps: DepsMut) -> Result<Response, ContractError> {
+    let validators: Vec<ValidatorInfo> = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| r.map(|(_, v)| v))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let commissions = bonded_commissions(deps.as_ref())?;
+    let mut events = Vec::new();
+    for mut v in validators {
+        match commissions.get(&v.address).copied() {
+            Some(commission) if commission != v.commission_rate => {
+                events.push(
+                    Event::new("validator_commission_changed")
+                        .add_attribute("validator", &v.address)
+                        .add_attribute("old", v.commission_rate.to_string())
+                        .add_attribute("new", commission.to_string()),
+                );
+                v.commission_rate = commission;
+            }
+            None if v.is_active => {
+                events.push(
+                    Event::new("validator_deactivated")
+                        .add_attribute("validator", &v.address)
+                        .add_attribute("reason", "not_bonded"),
+                );
+                v.is_active = false;
+            }
+            _ => continue,
+        }
+        save_validator(deps.storage, &v)?;
+    }
 
     Ok(Response::new()
-        .add_attribute("method", "update_validators")
-        .add_attribute("count", VALIDATORS.keys(deps.storage, None, None, Order::Ascending).count().to_string()))
+        .add_attribute("method", "sync_validators")
+        .add_attribute("changes", events.len().to_string())
+        .add_events(events))
+}
+
+/// Compare each tracked delegation with the staking module and write off stake lost to
+/// slashing. Losses reduce `total_regen_staked`, so they are socialised through the exchange
+/// rate. Losses above the slashing dust are counted against the validator and emitted as
+/// `validator_slashed` events; smaller ones are share rounding.
+pub fn execute_reconcile_slashing(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let validators: Vec<ValidatorInfo> = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| r.map(|(_, v)| v))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let dust = config.slashing_dust.unwrap_or(DEFAULT_SLASHING_DUST);
+    let mut events = Vec::new();
+    let mut written_down = Vec::new();
+    let mut total_loss = Uint128::zero();
+    for mut v in validators {
+        let on_chain = deps
+            .querier
+            .query_delegation(&env.contract.address, &v.address)?
+            .map(|d| d.amount.amount)
+            .unwrap_or_default();
+        if on_chain >= v.delegated_amount {
+            continue;
+        }
+        let loss = v.delegated_amount - on_chain;
+        total_loss = total_loss.checked_add(loss)?;
+        v.delegated_amount = on_chain;
+        if loss > dust {
+            v.slashing_events += 1;
+            events.push(
+                Event::new("validator_slashed")
+                    .add_attribute("validator", &v.address)
+                    .add_attribute("loss", loss)
+                    .add_attribute("delegated", on_chain),
+            );
+        }
+        save_validator(deps.storage, &v)?;
+        written_down.push(v.address);
+    }
+
+    let mut res = Response::new();
+    if !total_loss.is_zero() {
+        let mut state = STATE.load(deps.storage)?;
+        state.total_regen_staked = state.total_regen_staked.saturating_sub(total_loss);
+        state.last_update_time = env.block.time;
+        save_state(deps.storage, &state, env.block.time)?;
+        res = res
+            .add_submessages(delegation_report_msg(deps.storage, &config, written_down)?)
+            .add_submessages(hook_msgs(
+                deps.storage,
+                &state,
+                StakingHookEvent::Slashed { loss: total_loss },
+            )?);
+    }
+
+    Ok(res
+        .add_attribute("method", "reconcile_slashing")
+        .add_attribute("slashed_validators", events.len().to_string())
+        .add_attribute("loss", total_loss)
+        .add_events(events))
 }
 
 pub fn execute_pause(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
@@ -399,6 +1068,7 @@ pub fn execute_resume(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Res
     Ok(Response::new().add_attribute("method", "resume"))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_update_config(
     deps: DepsMut,
     _env: Env,
@@ -407,6 +1077,13 @@ pub fn execute_update_config(
     fee_rate: Option<Decimal>,
     max_validators: Option<u32>,
     dregen_token: Option<String>,
+    validator_registry: Option<String>,
+    rewards_contract: Option<String>,
+    drain_batch: Option<Uint128>,
+    delegation_strategy: Option<DelegationStrategyKind>,
+    stake_routing: Option<StakeRouting>,
+    max_unbond_validators: Option<u32>,
+    slashing_dust: Option<Uint128>,
 ) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
     if info.sender != config.admin {
@@ -428,37 +1105,390 @@ pub fn execute_update_config(
     if let Some(tok) = dregen_token {
         config.dregen_token = deps.api.addr_validate(&tok)?;
     }
+    if let Some(registry) = validator_registry {
+        config.validator_registry = Some(deps.api.addr_validate(&registry)?);
+    }
+    if let Some(rewards) = rewards_contract {
+        config.rewards_contract = Some(deps.api.addr_validate(&rewards)?);
+    }
+    validate_limits(drain_batch, max_unbond_validators)?;
+    if let Some(batch) = drain_batch {
+        config.drain_batch = Some(batch);
+    }
+    if let Some(strategy) = delegation_strategy {
+        config.delegation_strategy = strategy;
+    }
+    if let Some(routing) = stake_routing {
+        validate_stake_routing(&routing)?;
+        config.stake_routing = Some(routing);
+    }
+    if let Some(max) = max_unbond_validators {
+        config.max_unbond_validators = Some(max);
+    }
+    if let Some(dust) = slashing_dust {
+        config.slashing_dust = Some(dust);
+    }
 
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::new().add_attribute("method", "update_config"))
 }
 
+/// Replace a validator's tags. Registry-managed validators the hub has not delegated to yet
+/// are tracked from here on, like on their first delegation.
+pub fn execute_set_validator_tags(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    tags: Vec<ValidatorTag>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_admin(&info.sender, &config)?;
+    if tags.iter().any(|t| t.key.is_empty() || t.value.is_empty()) {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Tag key and value cannot be empty",
+        )));
+    }
+    let count = tags.len();
+    let validator = match VALIDATORS.may_load(deps.storage, address.clone())? {
+        Some(info) => ValidatorInfo { tags, ..info },
+        None if config.validator_registry.is_some() => ValidatorInfo {
+            tags,
+            ..new_validator_info(address.clone(), Decimal::zero(), env.block.time)
+        },
+        None => {
+            return Err(ContractError::ValidatorNotFound {
+                validator: address,
+            })
+        }
+    };
+    save_validator(deps.storage, &validator)?;
+    Ok(Response::new()
+        .add_attribute("method", "set_validator_tags")
+        .add_attribute("validator", address)
+        .add_attribute("tags", count.to_string()))
+}
+
+pub fn execute_update_diversity_constraints(
+    deps: DepsMut,
+    info: MessageInfo,
+    constraints: Vec<DiversityConstraint>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_admin(&info.sender, &config)?;
+    let mut min_total = Decimal::zero();
+    for c in &constraints {
+        let max = c.max_share.unwrap_or(Decimal::one());
+        let min = c.min_share.unwrap_or_default();
+        if c.key.is_empty() || max > Decimal::one() || min > max {
+            return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(format!(
+                "Invalid diversity constraint for {}",
+                c.key
+            ))));
+        }
+        if c.value.is_some() {
+            min_total = min_total.checked_add(min)?;
+        }
+    }
+    if min_total > Decimal::one() {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Minimum shares cannot exceed 100% in total",
+        )));
+    }
+    DIVERSITY_CONSTRAINTS.save(deps.storage, &constraints)?;
+    Ok(Response::new()
+        .add_attribute("method", "update_diversity_constraints")
+        .add_attribute("count", constraints.len().to_string()))
+}
+
+pub fn execute_update_decentralization(
+    deps: DepsMut,
+    info: MessageInfo,
+    params: Option<DecentralizationParams>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_admin(&info.sender, &config)?;
+    let Some(params) = params else {
+        DECENTRALIZATION.remove(deps.storage);
+        return Ok(Response::new()
+            .add_attribute("method", "update_decentralization")
+            .add_attribute("enabled", "false"));
+    };
+    // Voting power is only known through the registry oracle
+    if config.validator_registry.is_none() {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Decentralization requires a validator registry",
+        )));
+    }
+    let max_share = params.max_network_share.unwrap_or(Decimal::one());
+    if max_share.is_zero() || max_share > Decimal::one() || params.top_n_weight > Decimal::one() {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Invalid decentralization parameters",
+        )));
+    }
+    DECENTRALIZATION.save(deps.storage, &params)?;
+    Ok(Response::new()
+        .add_attribute("method", "update_decentralization")
+        .add_attribute("enabled", "true"))
+}
+
+/// Register a contract to be notified after stake, unbond, reward harvests and slashing
+/// (admin only)
+pub fn execute_add_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_admin(&info.sender, &config)?;
+    let contract = deps.api.addr_validate(&contract)?;
+    let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    if hooks.contains(&contract) {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Hook already registered",
+        )));
+    }
+    if hooks.len() >= MAX_HOOKS {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(format!(
+            "At most {MAX_HOOKS} hooks can be registered"
+        ))));
+    }
+    hooks.push(contract.clone());
+    HOOKS.save(deps.storage, &hooks)?;
+    Ok(Response::new()
+        .add_attribute("method", "add_hook")
+        .add_attribute("hook", contract))
+}
+
+/// Stop notifying a registered hook contract (admin only)
+pub fn execute_remove_hook(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_admin(&info.sender, &config)?;
+    let mut hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    let before = hooks.len();
+    hooks.retain(|hook| hook.as_str() != contract);
+    if hooks.len() == before {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Hook not registered",
+        )));
+    }
+    HOOKS.save(deps.storage, &hooks)?;
+    Ok(Response::new()
+        .add_attribute("method", "remove_hook")
+        .add_attribute("hook", contract))
+}
+
+/// Recompute the cached active set and totals from `VALIDATORS`, e.g. after a migration from a
+/// version without them (admin only)
+pub fn execute_rebuild_validator_cache(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_admin(&info.sender, &config)?;
+    let totals = rebuild_validator_cache(deps.storage)?;
+    Ok(Response::new()
+        .add_attribute("method", "rebuild_validator_cache")
+        .add_attribute("active", totals.active_count.to_string())
+        .add_attribute("total_delegated", totals.total_delegated))
+}
+
 // Helper functions
-fn get_active_validators(deps: Deps) -> Result<Vec<(String, Decimal)>, ContractError> {
-    let validators: Result<Vec<_>, ContractError> = VALIDATORS
+pub fn validate_stake_routing(routing: &StakeRouting) -> Result<(), ContractError> {
+    if routing.max_validators == 0 {
+        return Err(ContractError::Std(cosmwasm_std::StdError::generic_err(
+            "Stake routing must allow at least one validator",
+        )));
+    }
+    Ok(())
+}
+
+/// Reject limits set to zero, which would stop draining or unbonding altogether
+pub fn validate_limits(
+    drain_batch: Option<Uint128>,
+    max_unbond_validators: Option<u32>,
+) -> Result<(), ContractError> {
+    if drain_batch.map_or(false, |batch| batch.is_zero()) {
+        return Err(ContractError::ZeroLimit {
+            field: "drain_batch".to_string(),
+        });
+    }
+    if max_unbond_validators == Some(0) {
+        return Err(ContractError::ZeroLimit {
+            field: "max_unbond_validators".to_string(),
+        });
+    }
+    Ok(())
+}
+
+pub fn new_validator_info(address: String, weight: Decimal, now: Timestamp) -> ValidatorInfo {
+    ValidatorInfo {
+        address,
+        delegated_amount: Uint128::zero(),
+        weight,
+        last_reward_claim: now,
+        slashing_events: 0,
+        uptime_percentage: Decimal::percent(100),
+        commission_rate: Decimal::percent(10), // Default until read from the chain
+        is_active: true,
+        draining: false,
+        tags: vec![],
+    }
+}
+
+/// Record `amount` of new stake on `validator`. Registry-managed validators are tracked
+/// locally from their first delegation.
+fn add_delegation(
+    storage: &mut dyn Storage,
+    config: &Config,
+    validator: &str,
+    amount: Uint128,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    let mut info = match VALIDATORS.may_load(storage, validator.to_string())? {
+        Some(info) => info,
+        None if config.validator_registry.is_some() => {
+            new_validator_info(validator.to_string(), Decimal::zero(), now)
+        }
+        None => {
+            return Err(ContractError::ValidatorNotFound {
+                validator: validator.to_string(),
+            })
+        }
+    };
+    info.delegated_amount = info.delegated_amount.checked_add(amount)?;
+    save_validator(storage, &info)?;
+    Ok(())
+}
+
+/// Record `amount` of stake leaving `validator`
+fn remove_delegation(
+    storage: &mut dyn Storage,
+    validator: &str,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    let mut info = VALIDATORS
+        .may_load(storage, validator.to_string())?
+        .ok_or_else(|| ContractError::ValidatorNotFound {
+            validator: validator.to_string(),
+        })?;
+    info.delegated_amount = info.delegated_amount.checked_sub(amount)?;
+    save_validator(storage, &info)?;
+    Ok(())
+}
+
+fn active_count(storage: &dyn Storage) -> Result<u32, ContractError> {
+    Ok(VALIDATOR_TOTALS.may_load(storage)?.unwrap_or_default().active_count)
+}
+
+/// Active validators and weights. With a registry configured this is the registry's view
+/// for this hub, which must be registered there as a consumer.
+fn get_active_validators(deps: Deps, env: &Env) -> Result<Vec<(String, Decimal)>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if let Some(registry) = config.validator_registry {
+        let res: ActiveValidatorsResponse = deps.querier.query_wasm_smart(
+            registry,
+            &RegistryQueryMsg::ConsumerValidators {
+                consumer: env.contract.address.to_string(),
+            },
+        )?;
+        return Ok(res
+            .validators
+            .into_iter()
+            .map(|v| (v.address, v.weight))
+            .collect());
+    }
+
+    Ok(ACTIVE_VALIDATORS
         .range(deps.storage, None, None, Order::Ascending)
-        .filter_map(|item| match item {
-            Ok((addr, info)) if info.is_active => Some(Ok((addr, info.weight))),
-            Ok(_) => None,
-            Err(e) => Some(Err(ContractError::from(e))),
+        .collect::<StdResult<Vec<_>>>()?)
+}
+
+/// Active validators with weights adjusted to the diversity constraints; the targets for new
+/// stake and rebalancing
+fn get_target_weights(deps: Deps, env: &Env) -> Result<Vec<(String, Decimal)>, ContractError> {
+    let active = get_active_validators(deps, env)?;
+    let constraints = DIVERSITY_CONSTRAINTS.may_load(deps.storage)?.unwrap_or_default();
+    if constraints.is_empty() {
+        return Ok(active);
+    }
+    let tagged = active
+        .into_iter()
+        .map(|(address, weight)| {
+            let tags = VALIDATORS
+                .may_load(deps.storage, address.clone())?
+                .map(|v| v.tags)
+                .unwrap_or_default();
+            Ok((address, weight, tags))
         })
-        .collect();
-    validators
+        .collect::<Result<Vec<_>, ContractError>>()?;
+    Ok(apply_diversity_constraints(&tagged, &constraints)?)
 }
 
-fn get_active_validators_with_delegations(deps: Deps) -> Result<Vec<(String, Decimal)>, ContractError> {
-    let validators: Result<Vec<_>, ContractError> = VALIDATORS
-        .range(deps.storage, None, None, Order::Ascending)
-        .filter_map(|item| match item {
-            Ok((addr, info)) if info.is_active && !info.delegated_amount.is_zero() => {
-                let weight = Decimal::from_ratio(info.delegated_amount, Uint128::one());
-                Some(Ok((addr, weight)))
-            }
-            Ok(_) => None,
-            Err(e) => Some(Err(ContractError::from(e))),
+/// Split stake about to be delegated over `targets` with the configured strategy
+fn distribute_new_stake(
+    deps: Deps,
+    amount: Uint128,
+    targets: &[(String, Decimal)],
+) -> Result<Vec<(String, Uint128)>, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let candidates = stake_candidates(deps, &config, targets)?;
+    let strategy = strategy_for(config.delegation_strategy);
+    Ok(match config.stake_routing {
+        Some(routing) => route_stake(strategy, amount, &candidates, &routing)?,
+        None => strategy.allocate(amount, &candidates)?,
+    })
+}
+
+/// Candidates for the delegation strategy, skewed and capped by network voting power reported
+/// by the registry when decentralization is configured
+fn stake_candidates(
+    deps: Deps,
+    config: &Config,
+    targets: &[(String, Decimal)],
+) -> Result<Vec<Candidate>, ContractError> {
+    let decentralization = DECENTRALIZATION.may_load(deps.storage)?;
+    let mut candidates = targets
+        .iter()
+        .map(|(address, weight)| {
+            let info = VALIDATORS.may_load(deps.storage, address.clone())?;
+            Ok(Candidate {
+                address: address.clone(),
+                weight: *weight,
+                delegated: info.as_ref().map(|v| v.delegated_amount).unwrap_or_default(),
+                score: match &info {
+                    Some(v) => performance_score(v)?,
+                    None => Decimal::one(),
+                },
+                voting_power: None,
+                rank: None,
+                cap: None,
+            })
         })
-        .collect();
-    validators
+        .collect::<Result<Vec<_>, ContractError>>()?;
+
+    let needs_power = decentralization.is_some()
+        || config.delegation_strategy == DelegationStrategyKind::InverseVotingPower;
+    let mut bonded_tokens = None;
+    if let (true, Some(registry)) = (needs_power, &config.validator_registry) {
+        let power: VotingPowerResponse =
+            deps.querier.query_wasm_smart(registry, &RegistryQueryMsg::VotingPower {})?;
+        for c in candidates.iter_mut() {
+            if let Some(p) = power.validators.iter().find(|p| p.address == c.address) {
+                c.voting_power = p.voting_power;
+                c.rank = p.rank;
+            }
+        }
+        bonded_tokens = power.bonded_tokens;
+    }
+    if let Some(params) = decentralization {
+        apply_decentralization(&mut candidates, bonded_tokens, &params)?;
+    }
+    Ok(candidates)
 }
 
 fn query_total_rewards(_deps: Deps, _env: &Env) -> Result<Uint128, ContractError> {
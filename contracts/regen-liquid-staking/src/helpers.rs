@@ -1,4 +1,6 @@
-use cosmwasm_std::{Addr, MessageInfo, Uint128};
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, Decimal, Deps, MessageInfo, StdResult, Uint128, Validator};
 
 use crate::error::ContractError;
 use crate::state::Config;
@@ -28,4 +30,29 @@ pub fn ensure_admin(sender: &Addr, config: &Config) -> Result<(), ContractError>
         return Err(ContractError::Unauthorized {});
     }
     Ok(())
-}
\ No newline at end of file
+}
+/// On-chain commission of every validator in the bonded set, by operator address.
+/// `query_validator` also answers for jailed and unbonded validators the staking module still
+/// knows, so only `query_all_validators` tells whether a validator is bonded.
+pub fn bonded_commissions(deps: Deps) -> StdResult<BTreeMap<String, Decimal>> {
+    Ok(deps
+        .querier
+        .query_all_validators()?
+        .into_iter()
+        .map(|v| (v.address, v.commission))
+        .collect())
+}
+
+/// Look up a validator operator address in the bonded set. `query_validator` also answers for
+/// jailed and unbonded validators the staking module still knows, so the address is matched
+/// against `query_all_validators`. Operator addresses use the `valoper` prefix, so
+/// `addr_validate` cannot be used for them.
+pub fn validate_validator(deps: Deps, validator: &str) -> Result<Validator, ContractError> {
+    deps.querier
+        .query_all_validators()?
+        .into_iter()
+        .find(|v| v.address == validator)
+        .ok_or_else(|| ContractError::InvalidValidator {
+            address: validator.to_string(),
+        })
+}
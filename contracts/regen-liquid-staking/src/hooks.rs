@@ -0,0 +1,110 @@
+use cosmwasm_std::{to_binary, Reply, Response, StdResult, Storage, SubMsg, WasmMsg};
+use regen_types::{StakingHookEvent, StakingHookMsg};
+
+use crate::error::ContractError;
+use crate::state::{recorded_rate, State, HOOKS};
+
+pub const HOOK_REPLY_ID: u64 = 2;
+/// Most hooks the admin can register, bounding the fan-out of every user operation
+pub const MAX_HOOKS: usize = 10;
+/// Gas each hook may use, so an expensive hook fails on its own instead of exhausting the
+/// user's transaction
+pub const HOOK_GAS_LIMIT: u64 = 500_000;
+
+/// Notify every registered hook of `event` with the rate and totals of `state`. Hooks run as
+/// `reply_on_error` submessages, so a failing hook is rolled back on its own and the user
+/// operation still succeeds.
+pub fn hook_msgs(
+    storage: &dyn Storage,
+    state: &State,
+    event: StakingHookEvent,
+) -> StdResult<Vec<SubMsg>> {
+    let hooks = HOOKS.may_load(storage)?.unwrap_or_default();
+    if hooks.is_empty() {
+        return Ok(vec![]);
+    }
+    let msg = to_binary(&StakingHookMsg::StakingHook {
+        event,
+        exchange_rate: recorded_rate(state)?,
+        total_regen_staked: state.total_regen_staked,
+        total_dregen_supply: state.total_dregen_supply,
+    })?;
+    Ok(hooks
+        .into_iter()
+        .map(|hook| {
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: hook.to_string(),
+                    msg: msg.clone(),
+                    funds: vec![],
+                },
+                HOOK_REPLY_ID,
+            )
+            .with_gas_limit(HOOK_GAS_LIMIT)
+        })
+        .collect())
+}
+
+/// Swallow a hook failure, recording it for indexers
+pub fn handle_hook_reply(msg: Reply) -> Result<Response, ContractError> {
+    let error = msg.result.into_result().err().unwrap_or_default();
+    Ok(Response::new()
+        .add_attribute("method", "hook_failed")
+        .add_attribute("error", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{
+        from_json, Addr, CosmosMsg, Decimal, ReplyOn, SubMsgResult, Timestamp, Uint128,
+    };
+
+    #[test]
+    fn hooks_are_isolated_notifications() {
+        let mut storage = MockStorage::new();
+        let state = State {
+            total_regen_staked: Uint128::new(1_100),
+            total_dregen_supply: Uint128::new(1_000),
+            exchange_rate: Decimal::one(),
+            last_update_time: Timestamp::from_seconds(0),
+            total_rewards_claimed: Uint128::zero(),
+            pending_unbonding: Uint128::zero(),
+        };
+        let event = StakingHookEvent::Slashed {
+            loss: Uint128::new(10),
+        };
+        assert!(hook_msgs(&storage, &state, event.clone()).unwrap().is_empty());
+
+        HOOKS
+            .save(&mut storage, &vec![Addr::unchecked("vault"), Addr::unchecked("market")])
+            .unwrap();
+        let msgs = hook_msgs(&storage, &state, event.clone()).unwrap();
+        assert_eq!(msgs.len(), 2);
+        for sub in &msgs {
+            assert_eq!(sub.reply_on, ReplyOn::Error);
+            assert_eq!(sub.gas_limit, Some(HOOK_GAS_LIMIT));
+            let CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) = &sub.msg else {
+                panic!("unexpected hook message");
+            };
+            assert_eq!(
+                from_json::<StakingHookMsg>(msg).unwrap(),
+                StakingHookMsg::StakingHook {
+                    event: event.clone(),
+                    exchange_rate: Decimal::percent(110),
+                    total_regen_staked: state.total_regen_staked,
+                    total_dregen_supply: state.total_dregen_supply,
+                }
+            );
+        }
+
+        let res = handle_hook_reply(Reply {
+            id: HOOK_REPLY_ID,
+            result: SubMsgResult::Err("out of gas".to_string()),
+        })
+        .unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[1].value, "out of gas");
+    }
+}
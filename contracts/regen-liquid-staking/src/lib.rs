@@ -2,9 +2,12 @@ pub mod error;
 pub mod msg;
 pub mod state;
 pub mod math;
+pub mod strategy;
 pub mod helpers;
+pub mod lsm;
+pub mod hooks;
 pub mod execute;
 pub mod query;
 pub mod contract;
 
-pub use crate::contract::{execute, instantiate, query};
\ No newline at end of file
+pub use crate::contract::{execute, instantiate, migrate, query, reply};
\ No newline at end of file
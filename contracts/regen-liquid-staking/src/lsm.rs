@@ -0,0 +1,87 @@
+use cosmwasm_std::{Binary, Coin, CosmosMsg};
+
+/// Type URL of the liquid staking module message that converts tokenized shares
+/// back into a regular delegation owned by the sender.
+pub const MSG_REDEEM_TOKENS_FOR_SHARES: &str = "/cosmos.staking.v1beta1.MsgRedeemTokensForShares";
+
+/// Parse a tokenized share denom of the form `{validator}/{record_id}` and
+/// return the validator operator address it is backed by.
+pub fn parse_tokenized_share_denom(denom: &str) -> Option<String> {
+    let (validator, record_id) = denom.split_once('/')?;
+    if validator.is_empty() || record_id.is_empty() || record_id.parse::<u64>().is_err() {
+        return None;
+    }
+    Some(validator.to_string())
+}
+
+/// Build the stargate message redeeming `shares` into a delegation held by `delegator`
+pub fn redeem_tokens_for_shares_msg(delegator: &str, shares: &Coin) -> CosmosMsg {
+    // message Coin { string denom = 1; string amount = 2; }
+    let mut coin = Vec::new();
+    encode_string(&mut coin, 1, &shares.denom);
+    encode_string(&mut coin, 2, &shares.amount.to_string());
+
+    // message MsgRedeemTokensForShares { string delegator_address = 1; Coin amount = 2; }
+    let mut value = Vec::new();
+    encode_string(&mut value, 1, delegator);
+    encode_bytes(&mut value, 2, &coin);
+
+    CosmosMsg::Stargate {
+        type_url: MSG_REDEEM_TOKENS_FOR_SHARES.to_string(),
+        value: Binary::from(value),
+    }
+}
+
+fn encode_string(buf: &mut Vec<u8>, field: u64, value: &str) {
+    encode_bytes(buf, field, value.as_bytes());
+}
+
+fn encode_bytes(buf: &mut Vec<u8>, field: u64, value: &[u8]) {
+    // Wire type 2: length-delimited
+    encode_varint(buf, (field << 3) | 2);
+    encode_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+fn encode_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::coin;
+
+    #[test]
+    fn test_parse_tokenized_share_denom() {
+        assert_eq!(
+            parse_tokenized_share_denom("regenvaloper1abc/42"),
+            Some("regenvaloper1abc".to_string())
+        );
+        assert_eq!(parse_tokenized_share_denom("uregen"), None);
+        assert_eq!(parse_tokenized_share_denom("regenvaloper1abc/"), None);
+        assert_eq!(parse_tokenized_share_denom("ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"), None);
+    }
+
+    #[test]
+    fn test_redeem_tokens_for_shares_encoding() {
+        let msg = redeem_tokens_for_shares_msg("hub", &coin(5, "val/1"));
+        let CosmosMsg::Stargate { type_url, value } = msg else {
+            panic!("expected stargate message");
+        };
+        assert_eq!(type_url, MSG_REDEEM_TOKENS_FOR_SHARES);
+        assert_eq!(
+            value.as_slice(),
+            &[
+                0x0a, 3, b'h', b'u', b'b', // delegator_address
+                0x12, 10, // amount
+                0x0a, 5, b'v', b'a', b'l', b'/', b'1', // denom
+                0x12, 1, b'5', // amount
+            ]
+        );
+    }
+}
@@ -1,4 +1,11 @@
 use cosmwasm_std::{Decimal, StdError, StdResult, Uint128};
+use regen_types::ValidatorTag;
+
+use crate::state::{DecentralizationParams, DiversityConstraint};
+use crate::strategy::Candidate;
+
+/// Passes over the constraints before giving up on conflicting ones
+const DIVERSITY_ROUNDS: usize = 20;
 
 /// Calculate the exchange rate between REGEN and dREGEN
 /// Formula: exchange_rate = (total_regen_staked + accumulated_rewards) / total_dregen_supply
@@ -85,6 +92,204 @@ pub fn calculate_validator_distribution(
     Ok(distribution)
 }
 
+/// Calculate the redelegations that move current delegations towards the target weights.
+/// Validators absent from `targets` are treated as having zero weight.
+/// Returns `(src_validator, dst_validator, amount)` moves.
+pub fn calculate_rebalance_moves(
+    delegations: &[(String, Uint128)], // (address, delegated amount)
+    targets: &[(String, Decimal)],     // (address, weight)
+) -> StdResult<Vec<(String, String, Uint128)>> {
+    let total: Uint128 = delegations.iter().map(|(_, amount)| *amount).sum();
+    if total.is_zero() || targets.is_empty() {
+        return Ok(vec![]);
+    }
+    let target_amounts = calculate_validator_distribution(total, targets)?;
+    let target_of = |addr: &str| {
+        target_amounts
+            .iter()
+            .find(|(a, _)| a == addr)
+            .map(|(_, amount)| *amount)
+            .unwrap_or_default()
+    };
+    let current_of = |addr: &str| {
+        delegations
+            .iter()
+            .find(|(a, _)| a == addr)
+            .map(|(_, amount)| *amount)
+            .unwrap_or_default()
+    };
+
+    let mut surpluses: Vec<(String, Uint128)> = delegations
+        .iter()
+        .filter_map(|(addr, current)| {
+            let target = target_of(addr);
+            (*current > target).then(|| (addr.clone(), *current - target))
+        })
+        .collect();
+    let mut deficits: Vec<(String, Uint128)> = targets
+        .iter()
+        .filter_map(|(addr, _)| {
+            let current = current_of(addr);
+            let target = target_of(addr);
+            (target > current).then(|| (addr.clone(), target - current))
+        })
+        .collect();
+
+    let mut moves = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < surpluses.len() && j < deficits.len() {
+        let amount = surpluses[i].1.min(deficits[j].1);
+        moves.push((surpluses[i].0.clone(), deficits[j].0.clone(), amount));
+        surpluses[i].1 -= amount;
+        deficits[j].1 -= amount;
+        if surpluses[i].1.is_zero() {
+            i += 1;
+        }
+        if deficits[j].1.is_zero() {
+            j += 1;
+        }
+    }
+
+    Ok(moves)
+}
+
+/// Turn weights into target shares that respect the diversity constraints. Shares move
+/// proportionally between the validators inside and outside a constrained tag group, so
+/// validators with zero weight stay at zero. Constraints that cannot be met (e.g. a cap on
+/// the only region present) are left unmet, as are conflicts unresolved after a bounded
+/// number of passes.
+pub fn apply_diversity_constraints(
+    validators: &[(String, Decimal, Vec<ValidatorTag>)], // (address, weight, tags)
+    constraints: &[DiversityConstraint],
+) -> StdResult<Vec<(String, Decimal)>> {
+    let total: Decimal = validators.iter().map(|(_, weight, _)| *weight).sum();
+    if total.is_zero() || constraints.is_empty() {
+        return Ok(validators.iter().map(|(a, w, _)| (a.clone(), *w)).collect());
+    }
+    let mut shares = validators
+        .iter()
+        .map(|(_, weight, _)| {
+            weight
+                .checked_div(total)
+                .map_err(|e| StdError::generic_err(e.to_string()))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for _ in 0..DIVERSITY_ROUNDS {
+        let mut changed = false;
+        for c in constraints {
+            let mut values: Vec<&String> = match &c.value {
+                Some(value) => vec![value],
+                None => validators
+                    .iter()
+                    .flat_map(|(_, _, tags)| tags.iter().filter(|t| t.key == c.key))
+                    .map(|t| &t.value)
+                    .collect(),
+            };
+            values.sort();
+            values.dedup();
+            for value in values {
+                let members: Vec<bool> = validators
+                    .iter()
+                    .map(|(_, _, tags)| tags.iter().any(|t| t.key == c.key && &t.value == value))
+                    .collect();
+                let inside = group_total(&shares, &members, true);
+                let outside = group_total(&shares, &members, false);
+                if inside.is_zero() || outside.is_zero() {
+                    continue;
+                }
+                if let Some(max) = c.max_share.filter(|max| inside > *max) {
+                    shift_shares(&mut shares, &members, true, inside - max, inside, outside)?;
+                    changed = true;
+                } else if let Some(min) = c.min_share.filter(|min| inside < *min) {
+                    let amount = (min - inside).min(outside);
+                    shift_shares(&mut shares, &members, false, amount, outside, inside)?;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    Ok(validators
+        .iter()
+        .zip(shares)
+        .map(|((address, _, _), share)| (address.clone(), share))
+        .collect())
+}
+
+fn group_total(shares: &[Decimal], members: &[bool], inside: bool) -> Decimal {
+    shares
+        .iter()
+        .zip(members)
+        .filter(|(_, m)| **m == inside)
+        .map(|(s, _)| *s)
+        .sum()
+}
+
+/// Move `amount` of share from the `from_inside` side of `members` to the other side,
+/// proportionally to each validator's current share
+fn shift_shares(
+    shares: &mut [Decimal],
+    members: &[bool],
+    from_inside: bool,
+    amount: Decimal,
+    from_total: Decimal,
+    to_total: Decimal,
+) -> StdResult<()> {
+    for (share, member) in shares.iter_mut().zip(members) {
+        let from = *member == from_inside;
+        let delta = share
+            .checked_mul(amount)?
+            .checked_div(if from { from_total } else { to_total })
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+        *share = if from {
+            share.checked_sub(delta)?
+        } else {
+            share.checked_add(delta)?
+        };
+    }
+    Ok(())
+}
+
+/// Skew and cap `candidates` for decentralization: validators ranked within the top `top_n` by
+/// network voting power keep `top_n_weight` of their weight, and each is capped at the stake
+/// that keeps its share of `bonded_tokens` within `max_network_share`. Candidates without a
+/// reported rank or share are neither skewed nor capped.
+pub fn apply_decentralization(
+    candidates: &mut [Candidate],
+    bonded_tokens: Option<Uint128>,
+    params: &DecentralizationParams,
+) -> StdResult<()> {
+    let skewed: Vec<Decimal> = candidates
+        .iter()
+        .map(|c| match (params.top_n, c.rank) {
+            (Some(n), Some(rank)) if rank <= n => c.weight.checked_mul(params.top_n_weight),
+            _ => Ok(c.weight),
+        })
+        .collect::<Result<_, _>>()?;
+    // Skewing to nothing (every validator in the top N at zero weight) would refuse all stake
+    if skewed.iter().any(|w| !w.is_zero()) {
+        for (c, weight) in candidates.iter_mut().zip(skewed) {
+            c.weight = weight;
+        }
+    }
+    for c in candidates.iter_mut() {
+        if let (Some(max), Some(share), Some(bonded)) =
+            (params.max_network_share, c.voting_power, bonded_tokens)
+        {
+            let room = max
+                .saturating_sub(share)
+                .checked_mul(Decimal::from_ratio(bonded, 1u128))?
+                .to_uint_floor();
+            c.cap = Some(c.cap.map_or(room, |cap| cap.min(room)));
+        }
+    }
+    Ok(())
+}
+
 /// Calculate Annual Percentage Rate (APR) based on rewards
 pub fn calculate_apr(
     total_staked: Uint128,
@@ -108,6 +313,7 @@ pub fn calculate_apr(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::strategy::{DelegationStrategy, Weighted};
     use std::str::FromStr;
 
     #[test]
@@ -146,4 +352,151 @@ mod tests {
         assert_eq!(distribution[1].1, Uint128::new(300_000));
         assert_eq!(distribution[2].1, Uint128::new(200_000));
     }
+
+    #[test]
+    fn test_rebalance_moves() {
+        let delegations = vec![
+            ("validator1".to_string(), Uint128::new(700)),
+            ("validator2".to_string(), Uint128::new(300)),
+            ("validator3".to_string(), Uint128::new(0)),
+        ];
+        // validator1 was removed from the target set, validator4 is new
+        let targets = vec![
+            ("validator2".to_string(), Decimal::percent(50)),
+            ("validator3".to_string(), Decimal::percent(25)),
+            ("validator4".to_string(), Decimal::percent(25)),
+        ];
+
+        let moves = calculate_rebalance_moves(&delegations, &targets).unwrap();
+
+        assert_eq!(
+            moves,
+            vec![
+                ("validator1".to_string(), "validator2".to_string(), Uint128::new(200)),
+                ("validator1".to_string(), "validator3".to_string(), Uint128::new(250)),
+                ("validator1".to_string(), "validator4".to_string(), Uint128::new(250)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diversity_constraints() {
+        let tag = |key: &str, value: &str| ValidatorTag {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let validators = vec![
+            ("a".to_string(), Decimal::percent(50), vec![tag("region", "eu")]),
+            ("b".to_string(), Decimal::percent(30), vec![tag("region", "eu")]),
+            (
+                "c".to_string(),
+                Decimal::percent(20),
+                vec![tag("region", "us"), tag("community", "true")],
+            ),
+        ];
+        let shares = |constraints: &[DiversityConstraint]| -> Vec<Decimal> {
+            apply_diversity_constraints(&validators, constraints)
+                .unwrap()
+                .into_iter()
+                .map(|(_, share)| share)
+                .collect()
+        };
+        let constraint = |key: &str, value: Option<&str>, max: Option<u64>, min: Option<u64>| {
+            DiversityConstraint {
+                key: key.to_string(),
+                value: value.map(str::to_string),
+                max_share: max.map(Decimal::percent),
+                min_share: min.map(Decimal::percent),
+            }
+        };
+
+        // The EU cap moves 20% to the US validator, split 5:3 off the EU validators
+        let capped = shares(&[constraint("region", Some("eu"), Some(60), None)]);
+        assert_eq!(
+            capped,
+            vec![Decimal::permille(375), Decimal::permille(225), Decimal::percent(40)]
+        );
+
+        // Without a value, every region is capped
+        let any_region = shares(&[constraint("region", None, Some(50), None)]);
+        assert_eq!(
+            any_region,
+            vec![
+                Decimal::from_ratio(5u128, 16u128),
+                Decimal::from_ratio(3u128, 16u128),
+                Decimal::percent(50),
+            ]
+        );
+
+        // Community validators get at least their floor
+        let floored = shares(&[constraint("community", Some("true"), None, Some(30))]);
+        assert_eq!(
+            floored,
+            vec![
+                Decimal::from_ratio(7u128, 16u128),
+                Decimal::from_ratio(21u128, 80u128),
+                Decimal::percent(30),
+            ]
+        );
+
+        // No constraints keeps the weights
+        assert_eq!(
+            shares(&[]),
+            vec![Decimal::percent(50), Decimal::percent(30), Decimal::percent(20)]
+        );
+    }
+
+    #[test]
+    fn test_decentralization() {
+        let params = DecentralizationParams {
+            max_network_share: Some(Decimal::percent(10)),
+            top_n: Some(10),
+            top_n_weight: Decimal::percent(50),
+        };
+        let bonded = Some(Uint128::new(1_000_000));
+        let candidate = |address: &str, rank: Option<u32>, share: Option<Decimal>| Candidate {
+            address: address.to_string(),
+            weight: Decimal::one(),
+            delegated: Uint128::zero(),
+            score: Decimal::one(),
+            voting_power: share,
+            rank,
+            cap: None,
+        };
+        let distribute = |shares: [Decimal; 3]| -> Vec<(String, Uint128)> {
+            let mut candidates = vec![
+                candidate("a", Some(1), Some(shares[0])),
+                candidate("b", Some(5), Some(shares[1])),
+                candidate("c", Some(40), Some(shares[2])),
+            ];
+            apply_decentralization(&mut candidates, bonded, &params).unwrap();
+            Weighted.allocate(Uint128::new(30_000), &candidates).unwrap()
+        };
+        let alloc = |amounts: [u128; 3]| -> Vec<(String, Uint128)> {
+            ["a", "b", "c"]
+                .iter()
+                .zip(amounts)
+                .map(|(a, amount)| (a.to_string(), Uint128::new(amount)))
+                .collect()
+        };
+
+        // Top-10 validators get half weight; nobody is near the cap
+        let low = [Decimal::permille(90), Decimal::percent(2), Decimal::percent(1)];
+        assert_eq!(distribute(low), alloc([7_500, 7_500, 15_000]));
+
+        // `a` only has room for 1_000 before reaching 10%; the rest goes to the others by weight
+        let near_cap = [Decimal::permille(99), Decimal::percent(2), Decimal::percent(1)];
+        assert_eq!(distribute(near_cap), alloc([1_000, 9_666, 19_334]));
+
+        // With every validator at the cap, stake is still placed by weight
+        assert_eq!(distribute([Decimal::percent(10); 3]), alloc([7_500, 7_500, 15_000]));
+
+        // Without reported shares or ranks nothing is skewed or capped
+        let mut unknown = vec![candidate("a", None, None), candidate("b", None, None)];
+        apply_decentralization(&mut unknown, bonded, &params).unwrap();
+        assert_eq!(unknown.iter().map(|c| (c.weight, c.cap)).collect::<Vec<_>>(), vec![
+            (Decimal::one(), None),
+            (Decimal::one(), None),
+        ]);
+    }
 }
\ No newline at end of file
@@ -1,7 +1,12 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{Coin, Decimal, Uint128};
 
-use crate::state::{UnbondingRequest, ValidatorInfo};
+use regen_types::ValidatorTag;
+
+use crate::state::{
+    DecentralizationParams, DelegationStrategyKind, DiversityConstraint, RateSnapshot,
+    StakeRouting, UnbondingRequest, ValidatorInfo, ValidatorTotals,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -13,41 +18,86 @@ pub struct InstantiateMsg {
     /// Optional at instantiate. If omitted, you must set it later via UpdateConfig.
     pub dregen_token: Option<String>,
     pub validators: Vec<ValidatorParams>,
+    /// Optional `regen-validators` contract to source the active set and weights from
+    pub validator_registry: Option<String>,
+    /// Optional `regen-rewards` contract to record harvested rewards and claims in
+    pub rewards_contract: Option<String>,
+    /// Maximum stake moved off each draining validator per crank; unbounded if omitted
+    pub drain_batch: Option<Uint128>,
+    /// Split of new stake across validators; weighted if omitted
+    pub delegation_strategy: Option<DelegationStrategyKind>,
+    /// Bounded routing of deposits; every deposit is split over all targets if omitted
+    pub stake_routing: Option<StakeRouting>,
+    /// Most validators one unbonding undelegates from; unbounded if omitted
+    pub max_unbond_validators: Option<u32>,
+    /// Largest shortfall not counted as a slashing event; 100 uregen if omitted
+    pub slashing_dust: Option<Uint128>,
 }
 
-#[cw_serde]
-pub struct ValidatorParams {
-    pub address: String,
-    pub weight: Decimal,
-}
+pub use regen_types::ValidatorParams;
 
 #[cw_serde]
 pub enum ExecuteMsg {
     /// Stake REGEN tokens and mint dREGEN
     Stake {},
+    /// Liquid-stake LSM tokenized delegation shares sent as funds and mint dREGEN
+    StakeTokenizedShares {},
     /// Initiate unbonding process
     Unbond { dregen_amount: Uint128 },
     /// Claim completed unbonding
     ClaimUnbonding { unbonding_id: u64 },
     /// Rebalance delegations across validators
     Rebalance {},
+    /// Migrate stake off validators removed from the active set (permissionless crank)
+    DrainValidators {},
+    /// Refresh commission from the chain and deactivate validators that left the bonded set (permissionless)
+    SyncValidators {},
+    /// Write down stake lost to slashing by comparing delegations on chain (permissionless)
+    ReconcileSlashing {},
     /// Claim rewards from all validators
     ClaimRewards {},
     /// Update validator set
     UpdateValidators { validators: Vec<ValidatorParams> },
+    /// Active set pushed by the configured validator registry; stored until `ApplyRegistryUpdate`
+    RegistryUpdate { validators: Vec<ValidatorParams> },
+    /// Apply the set last pushed by the registry and rebalance to it (permissionless crank)
+    ApplyRegistryUpdate {},
     /// Emergency pause contract
     Pause {},
     /// Resume contract operations
     Resume {},
+    /// Replace a validator's tags used by the diversity constraints (admin only)
+    SetValidatorTags { address: String, tags: Vec<ValidatorTag> },
+    /// Replace the diversity constraints applied to stake distribution and rebalancing (admin only)
+    UpdateDiversityConstraints { constraints: Vec<DiversityConstraint> },
+    /// Cap and skew new stake by network voting power from the registry oracle; `None`
+    /// disables it (admin only)
+    UpdateDecentralization { params: Option<DecentralizationParams> },
+    /// Recompute the cached active set and validator totals from the validator map (admin only)
+    RebuildValidatorCache {},
+    /// Notify `contract` after stake, unbond, reward harvests and slashing (admin only)
+    AddHook { contract: String },
+    /// Stop notifying a hook contract (admin only)
+    RemoveHook { contract: String },
     /// Update configuration
     UpdateConfig {
         admin: Option<String>,
         fee_rate: Option<Decimal>,
         max_validators: Option<u32>,
         dregen_token: Option<String>,
+        validator_registry: Option<String>,
+        rewards_contract: Option<String>,
+        drain_batch: Option<Uint128>,
+        delegation_strategy: Option<DelegationStrategyKind>,
+        stake_routing: Option<StakeRouting>,
+        max_unbond_validators: Option<u32>,
+        slashing_dust: Option<Uint128>,
     },
 }
 
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
@@ -60,9 +110,12 @@ pub enum QueryMsg {
     /// Get exchange rate between REGEN and dREGEN
     #[returns(ExchangeRateResponse)]
     ExchangeRate {},
-    /// Get validator information
+    /// Get validator information, a page at a time
     #[returns(ValidatorsResponse)]
-    Validators {},
+    Validators {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     /// Get user's unbonding requests
     #[returns(UnbondingResponse)]
     Unbonding { user: String },
@@ -72,6 +125,28 @@ pub enum QueryMsg {
     /// Simulate unbonding operation
     #[returns(SimulateUnbondResponse)]
     SimulateUnbond { dregen_amount: Uint128 },
+    /// Configured diversity constraints
+    #[returns(Vec<DiversityConstraint>)]
+    DiversityConstraints {},
+    /// Configured decentralization parameters, if any
+    #[returns(Option<DecentralizationParams>)]
+    Decentralization {},
+    /// Compare the cached active set and totals with a full scan of the validators
+    #[returns(ValidatorCacheResponse)]
+    ValidatorCache {},
+    /// Exchange-rate snapshots with `start <= time < end` (seconds), oldest first
+    #[returns(RateHistoryResponse)]
+    RateHistory {
+        start: Option<u64>,
+        end: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Time-weighted average exchange rate over `start..end`, `end` defaulting to now
+    #[returns(TwapRateResponse)]
+    TwapRate { start: u64, end: Option<u64> },
+    /// Contracts notified of stake, unbond, reward harvest and slashing events
+    #[returns(HooksResponse)]
+    Hooks {},
 }
 
 // Response types
@@ -84,6 +159,13 @@ pub struct ConfigResponse {
     pub max_validators: u32,
     pub min_delegation: Uint128,
     pub pause_contract: bool,
+    pub validator_registry: Option<String>,
+    pub rewards_contract: Option<String>,
+    pub drain_batch: Option<Uint128>,
+    pub delegation_strategy: DelegationStrategyKind,
+    pub stake_routing: Option<StakeRouting>,
+    pub max_unbond_validators: Option<u32>,
+    pub slashing_dust: Option<Uint128>,
 }
 
 #[cw_serde]
@@ -102,11 +184,37 @@ pub struct ExchangeRateResponse {
     pub last_updated: u64,
 }
 
+#[cw_serde]
+pub struct RateHistoryResponse {
+    pub snapshots: Vec<RateSnapshot>,
+}
+
+#[cw_serde]
+pub struct TwapRateResponse {
+    pub rate: Decimal,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[cw_serde]
+pub struct HooksResponse {
+    pub hooks: Vec<String>,
+}
+
 #[cw_serde]
 pub struct ValidatorsResponse {
     pub validators: Vec<ValidatorInfo>,
 }
 
+#[cw_serde]
+pub struct ValidatorCacheResponse {
+    pub consistent: bool,
+    pub cached: ValidatorTotals,
+    pub computed: ValidatorTotals,
+    /// Validators whose cached active weight differs from the validator map
+    pub mismatched: Vec<String>,
+}
+
 #[cw_serde]
 pub struct UnbondingResponse {
     pub requests: Vec<UnbondingRequest>,
@@ -1,10 +1,16 @@
-use cosmwasm_std::{Deps, Env, Order, StdResult, Uint128};
+use cosmwasm_std::{Decimal, Deps, Env, Order, StdResult, Uint128};
+use cw_storage_plus::Bound;
 use crate::math::{calculate_dregen_mint_amount, calculate_exchange_rate, calculate_fee, calculate_regen_return_amount};
 use crate::msg::{
-    ConfigResponse, ExchangeRateResponse, SimulateStakeResponse, SimulateUnbondResponse,
-    StateResponse, UnbondingResponse, ValidatorsResponse,
+    ConfigResponse, ExchangeRateResponse, HooksResponse, RateHistoryResponse,
+    SimulateStakeResponse, SimulateUnbondResponse, StateResponse, TwapRateResponse,
+    UnbondingResponse, ValidatorCacheResponse, ValidatorsResponse,
+};
+use crate::state::{
+    latest_rate_snapshot, scan_validators, twap_rate, DecentralizationParams,
+    DiversityConstraint, ACTIVE_VALIDATORS, CONFIG, DECENTRALIZATION, DIVERSITY_CONSTRAINTS,
+    HOOKS, RATE_SNAPSHOTS, STATE, UNBONDING_REQUESTS, VALIDATORS, VALIDATOR_TOTALS,
 };
-use crate::state::{CONFIG, STATE, UNBONDING_REQUESTS, VALIDATORS};
 
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let cfg = CONFIG.load(deps.storage)?;
@@ -16,6 +22,13 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         max_validators: cfg.max_validators,
         min_delegation: cfg.min_delegation,
         pause_contract: cfg.pause_contract,
+        validator_registry: cfg.validator_registry.map(|r| r.to_string()),
+        rewards_contract: cfg.rewards_contract.map(|r| r.to_string()),
+        drain_batch: cfg.drain_batch,
+        delegation_strategy: cfg.delegation_strategy,
+        stake_routing: cfg.stake_routing,
+        max_unbond_validators: cfg.max_unbond_validators,
+        slashing_dust: cfg.slashing_dust,
     })
 }
 
@@ -39,20 +52,100 @@ pub fn query_exchange_rate(deps: Deps, env: Env) -> StdResult<ExchangeRateRespon
         st.total_dregen_supply,
         total_rewards,
     )?;
+    // The rate as of the last time it changed, not the time of the query
+    let last_updated = latest_rate_snapshot(deps.storage, env.block.time.seconds())?
+        .map(|s| s.changed_at)
+        .unwrap_or(st.last_update_time);
     Ok(ExchangeRateResponse {
         rate,
-        last_updated: env.block.time.seconds(),
+        last_updated: last_updated.seconds(),
     })
 }
 
-pub fn query_validators(deps: Deps) -> StdResult<ValidatorsResponse> {
+pub fn query_hooks(deps: Deps) -> StdResult<HooksResponse> {
+    let hooks = HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(HooksResponse {
+        hooks: hooks.into_iter().map(|h| h.to_string()).collect(),
+    })
+}
+
+pub fn query_rate_history(
+    deps: Deps,
+    start: Option<u64>,
+    end: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<RateHistoryResponse> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let snapshots = RATE_SNAPSHOTS
+        .range(
+            deps.storage,
+            start.map(Bound::inclusive),
+            end.map(Bound::exclusive),
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|r| r.map(|(_, s)| s))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(RateHistoryResponse { snapshots })
+}
+
+pub fn query_twap_rate(
+    deps: Deps,
+    env: Env,
+    start: u64,
+    end: Option<u64>,
+) -> StdResult<TwapRateResponse> {
+    let now = env.block.time.seconds();
+    let end = end.unwrap_or(now).min(now);
+    let rate = twap_rate(deps.storage, start, end)?;
+    Ok(TwapRateResponse { rate, start, end })
+}
+
+pub fn query_validators(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ValidatorsResponse> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let start = start_after.map(Bound::exclusive);
     let vals: Vec<_> = VALIDATORS
-        .range(deps.storage, None, None, Order::Ascending)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
         .map(|r| r.map(|(_, v)| v))
         .collect::<StdResult<Vec<_>>>()?;
     Ok(ValidatorsResponse { validators: vals })
 }
 
+pub fn query_diversity_constraints(deps: Deps) -> StdResult<Vec<DiversityConstraint>> {
+    Ok(DIVERSITY_CONSTRAINTS.may_load(deps.storage)?.unwrap_or_default())
+}
+
+pub fn query_decentralization(deps: Deps) -> StdResult<Option<DecentralizationParams>> {
+    DECENTRALIZATION.may_load(deps.storage)
+}
+
+pub fn query_validator_cache(deps: Deps) -> StdResult<ValidatorCacheResponse> {
+    let cached = VALIDATOR_TOTALS.may_load(deps.storage)?.unwrap_or_default();
+    let (computed, active) = scan_validators(deps.storage)?;
+    let cached_active: Vec<(String, Decimal)> = ACTIVE_VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    let mut mismatched: Vec<String> = active
+        .iter()
+        .filter(|entry| !cached_active.contains(entry))
+        .chain(cached_active.iter().filter(|entry| !active.contains(entry)))
+        .map(|(address, _)| address.clone())
+        .collect();
+    mismatched.sort();
+    mismatched.dedup();
+    Ok(ValidatorCacheResponse {
+        consistent: cached == computed && mismatched.is_empty(),
+        cached,
+        computed,
+        mismatched,
+    })
+}
+
 pub fn query_unbonding(deps: Deps, user: String) -> StdResult<UnbondingResponse> {
     let addr = deps.api.addr_validate(&user)?;
     let mut requests = Vec::new();
@@ -1,8 +1,17 @@
-use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Decimal, Order, StdError, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use regen_types::{ValidatorParams, ValidatorTag};
+
+use crate::math::calculate_exchange_rate;
+
+/// Largest shortfall against the staking module written off as rounding when unconfigured
+pub const DEFAULT_SLASHING_DUST: Uint128 = Uint128::new(100);
+/// Seconds after which an unchanged exchange rate is snapshotted again on the next state write
+pub const RATE_SNAPSHOT_INTERVAL: u64 = 86_400;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub admin: Addr,
@@ -12,6 +21,42 @@ pub struct Config {
     pub max_validators: u32,
     pub min_delegation: Uint128,
     pub pause_contract: bool,
+    /// When set, the active validator set and weights are read from this `regen-validators` contract
+    pub validator_registry: Option<Addr>,
+    /// `regen-rewards` ledger that receives reward and claim records; the hub must be its distributor
+    pub rewards_contract: Option<Addr>,
+    /// Maximum stake moved off each draining validator per `DrainValidators` crank; unbounded if unset
+    pub drain_batch: Option<Uint128>,
+    /// How new stake is split across validators
+    #[serde(default)]
+    pub delegation_strategy: DelegationStrategyKind,
+    /// Route each deposit to a few underweight validators instead of splitting it over all
+    pub stake_routing: Option<StakeRouting>,
+    /// Most validators a single unbonding undelegates from, unless they hold too little
+    pub max_unbond_validators: Option<u32>,
+    /// Shortfalls up to this amount are share rounding, not slashing; `DEFAULT_SLASHING_DUST`
+    /// if unset
+    pub slashing_dust: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakeRouting {
+    /// Most validators a single deposit is delegated to
+    pub max_validators: u32,
+    /// Smallest delegation made to a validator; smaller parts join the largest one
+    pub min_delegation: Uint128,
+}
+
+/// Selects the `strategy::DelegationStrategy` used for new stake
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationStrategyKind {
+    Equal,
+    #[default]
+    Weighted,
+    ScoreBased,
+    InverseVotingPower,
+    FillUnderweight,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -34,6 +79,33 @@ pub struct ValidatorInfo {
     pub uptime_percentage: Decimal,
     pub commission_rate: Decimal,
     pub is_active: bool,
+    /// Removed from the active set; stake is being migrated before the entry is deleted
+    #[serde(default)]
+    pub draining: bool,
+    /// Labels matched by the diversity constraints
+    #[serde(default)]
+    pub tags: Vec<ValidatorTag>,
+}
+
+/// Bounds on the combined target share of validators tagged `key=value`. Without a `value`,
+/// the bounds apply to each value of `key` separately (e.g. at most 30% in any one region).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DiversityConstraint {
+    pub key: String,
+    pub value: Option<String>,
+    pub max_share: Option<Decimal>,
+    pub min_share: Option<Decimal>,
+}
+
+/// How new stake accounts for validators' existing network voting power, as reported by the
+/// registry oracle
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DecentralizationParams {
+    /// Network voting power share our delegations may not push a validator above
+    pub max_network_share: Option<Decimal>,
+    /// Validators ranked within the top `top_n` by voting power get `top_n_weight` of their weight
+    pub top_n: Option<u32>,
+    pub top_n_weight: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -52,11 +124,295 @@ pub struct RewardDistribution {
     pub distribution_time: Timestamp,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingShareRedemption {
+    pub staker: Addr,
+    pub validator: String,
+    pub shares: Uint128,
+    /// Hub delegation to `validator` before the shares were redeemed
+    pub delegation_before: Uint128,
+}
+
+/// Exchange rate and totals at `time`. `cumulative_rate` is the sum of rate times seconds
+/// elapsed from the first snapshot up to `time`, so averages over any window need two lookups.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RateSnapshot {
+    pub time: Timestamp,
+    pub exchange_rate: Decimal,
+    pub total_regen_staked: Uint128,
+    pub total_dregen_supply: Uint128,
+    pub cumulative_rate: Decimal,
+    /// When the rate last changed; later snapshots of an unchanged rate carry it forward
+    pub changed_at: Timestamp,
+}
+
+/// Aggregates over `VALIDATORS`, kept in step with every write so readers avoid full scans
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct ValidatorTotals {
+    pub active_count: u32,
+    pub active_weight: Decimal,
+    pub total_delegated: Uint128,
+}
+
 // Storage items
 pub const CONFIG: Item<Config> = Item::new("config");
+/// Written through `save_state`, which snapshots the exchange rate into `RATE_SNAPSHOTS`
 pub const STATE: Item<State> = Item::new("state");
+/// Exchange-rate history keyed by block time (seconds)
+pub const RATE_SNAPSHOTS: Map<u64, RateSnapshot> = Map::new("rate_snapshots");
+/// Contracts notified after stake, unbond, reward harvests and slashing (admin managed)
+pub const HOOKS: Item<Vec<Addr>> = Item::new("hooks");
+/// Written only through `save_validator` and `remove_validator`, which maintain the derived
+/// `ACTIVE_VALIDATORS` and `VALIDATOR_TOTALS`
 pub const VALIDATORS: Map<String, ValidatorInfo> = Map::new("validators");
+/// Weights of the active validators in `VALIDATORS`
+pub const ACTIVE_VALIDATORS: Map<String, Decimal> = Map::new("active_validators");
+pub const VALIDATOR_TOTALS: Item<ValidatorTotals> = Item::new("validator_totals");
 pub const UNBONDING_REQUESTS: Map<u64, UnbondingRequest> = Map::new("unbonding");
 pub const USER_UNBONDING: Map<(&Addr, u64), bool> = Map::new("user_unbonding");
 pub const REWARD_HISTORY: Map<u64, RewardDistribution> = Map::new("rewards");
-pub const NEXT_UNBONDING_ID: Item<u64> = Item::new("next_unbonding_id");
\ No newline at end of file
+pub const NEXT_UNBONDING_ID: Item<u64> = Item::new("next_unbonding_id");
+/// Empty unless configured through `UpdateDiversityConstraints`
+pub const DIVERSITY_CONSTRAINTS: Item<Vec<DiversityConstraint>> = Item::new("diversity_constraints");
+/// Unset unless configured through `UpdateDecentralization`; new stake then follows weights only
+pub const DECENTRALIZATION: Item<DecentralizationParams> = Item::new("decentralization");
+/// Stake undelegated from draining validators, keyed by unbonding completion time (seconds)
+pub const RESTAKE_QUEUE: Map<u64, Uint128> = Map::new("restake_queue");
+/// Active set last pushed by the registry, applied by the `ApplyRegistryUpdate` crank
+pub const PENDING_REGISTRY_SET: Item<Vec<ValidatorParams>> = Item::new("pending_registry_set");
+pub const PENDING_SHARE_REDEMPTION: Item<PendingShareRedemption> = Item::new("pending_share_redemption");
+
+/// Save `state` and snapshot the exchange rate it implies when that differs from the last
+/// snapshot or the last one is older than `RATE_SNAPSHOT_INTERVAL`. Rewards not yet harvested
+/// are not part of the recorded rate.
+pub fn save_state(storage: &mut dyn Storage, state: &State, now: Timestamp) -> StdResult<()> {
+    STATE.save(storage, state)?;
+    let rate = recorded_rate(state)?;
+    let (cumulative_rate, changed_at) = match latest_rate_snapshot(storage, now.seconds())? {
+        None => (Decimal::zero(), now),
+        Some(last) => {
+            let changed = last.exchange_rate != rate;
+            let due = now.seconds() >= last.time.seconds() + RATE_SNAPSHOT_INTERVAL;
+            if !changed && !due {
+                return Ok(());
+            }
+            (
+                cumulative_rate_at(&last, now.seconds())?,
+                if changed { now } else { last.changed_at },
+            )
+        }
+    };
+    RATE_SNAPSHOTS.save(
+        storage,
+        now.seconds(),
+        &RateSnapshot {
+            time: now,
+            exchange_rate: rate,
+            total_regen_staked: state.total_regen_staked,
+            total_dregen_supply: state.total_dregen_supply,
+            cumulative_rate,
+            changed_at,
+        },
+    )
+}
+
+/// Exchange rate implied by `state`, as recorded in snapshots and sent to hooks
+pub fn recorded_rate(state: &State) -> StdResult<Decimal> {
+    calculate_exchange_rate(state.total_regen_staked, state.total_dregen_supply, Uint128::zero())
+}
+
+/// Most recent snapshot taken at or before `time` (seconds)
+pub fn latest_rate_snapshot(storage: &dyn Storage, time: u64) -> StdResult<Option<RateSnapshot>> {
+    RATE_SNAPSHOTS
+        .range(storage, None, Some(Bound::inclusive(time)), Order::Descending)
+        .next()
+        .transpose()
+        .map(|s| s.map(|(_, s)| s))
+}
+
+/// Time-weighted average exchange rate over `start..end` (seconds)
+pub fn twap_rate(storage: &dyn Storage, start: u64, end: u64) -> StdResult<Decimal> {
+    if end <= start {
+        return Err(StdError::generic_err("TWAP window must end after it starts"));
+    }
+    let cumulative = |time: u64| -> StdResult<Decimal> {
+        let snapshot = latest_rate_snapshot(storage, time)?.ok_or_else(|| {
+            StdError::generic_err(format!("No exchange rate history at {time}"))
+        })?;
+        cumulative_rate_at(&snapshot, time)
+    };
+    cumulative(end)?
+        .checked_sub(cumulative(start)?)?
+        .checked_div(Decimal::from_ratio(end - start, 1u128))
+        .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+fn cumulative_rate_at(snapshot: &RateSnapshot, time: u64) -> StdResult<Decimal> {
+    let elapsed = Decimal::from_ratio(time - snapshot.time.seconds(), 1u128);
+    Ok(snapshot.cumulative_rate.checked_add(snapshot.exchange_rate.checked_mul(elapsed)?)?)
+}
+
+/// Save `info` and update the derived active set and totals
+pub fn save_validator(storage: &mut dyn Storage, info: &ValidatorInfo) -> StdResult<()> {
+    let previous = VALIDATORS.may_load(storage, info.address.clone())?;
+    VALIDATORS.save(storage, info.address.clone(), info)?;
+    update_derived(storage, previous.as_ref(), Some(info))
+}
+
+/// Delete a validator and update the derived active set and totals
+pub fn remove_validator(storage: &mut dyn Storage, address: &str) -> StdResult<()> {
+    let previous = VALIDATORS.may_load(storage, address.to_string())?;
+    VALIDATORS.remove(storage, address.to_string());
+    update_derived(storage, previous.as_ref(), None)
+}
+
+fn update_derived(
+    storage: &mut dyn Storage,
+    previous: Option<&ValidatorInfo>,
+    next: Option<&ValidatorInfo>,
+) -> StdResult<()> {
+    let mut totals = VALIDATOR_TOTALS.may_load(storage)?.unwrap_or_default();
+    if let Some(v) = previous {
+        totals.total_delegated = totals.total_delegated.checked_sub(v.delegated_amount)?;
+        if v.is_active {
+            totals.active_count = totals.active_count.checked_sub(1).ok_or_else(|| {
+                StdError::generic_err("Validator cache out of sync; rebuild it")
+            })?;
+            totals.active_weight = totals.active_weight.checked_sub(v.weight)?;
+            ACTIVE_VALIDATORS.remove(storage, v.address.clone());
+        }
+    }
+    if let Some(v) = next {
+        totals.total_delegated = totals.total_delegated.checked_add(v.delegated_amount)?;
+        if v.is_active {
+            totals.active_count += 1;
+            totals.active_weight = totals.active_weight.checked_add(v.weight)?;
+            ACTIVE_VALIDATORS.save(storage, v.address.clone(), &v.weight)?;
+        }
+    }
+    VALIDATOR_TOTALS.save(storage, &totals)
+}
+
+/// Replace the cached active set and totals with a full scan of `VALIDATORS`
+pub fn rebuild_validator_cache(storage: &mut dyn Storage) -> StdResult<ValidatorTotals> {
+    let stale: Vec<String> = ACTIVE_VALIDATORS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for address in stale {
+        ACTIVE_VALIDATORS.remove(storage, address);
+    }
+    let (totals, active) = scan_validators(storage)?;
+    for (address, weight) in &active {
+        ACTIVE_VALIDATORS.save(storage, address.clone(), weight)?;
+    }
+    VALIDATOR_TOTALS.save(storage, &totals)?;
+    Ok(totals)
+}
+
+/// Active set and totals recomputed from a full scan of `VALIDATORS`
+pub fn scan_validators(
+    storage: &dyn Storage,
+) -> StdResult<(ValidatorTotals, Vec<(String, Decimal)>)> {
+    let mut totals = ValidatorTotals::default();
+    let mut active = Vec::new();
+    for item in VALIDATORS.range(storage, None, None, Order::Ascending) {
+        let (address, v) = item?;
+        totals.total_delegated = totals.total_delegated.checked_add(v.delegated_amount)?;
+        if v.is_active {
+            totals.active_count += 1;
+            totals.active_weight = totals.active_weight.checked_add(v.weight)?;
+            active.push((address, v.weight));
+        }
+    }
+    Ok((totals, active))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn rate_snapshots_and_twap() {
+        let mut storage = MockStorage::new();
+        let state = |staked: u128, supply: u128| State {
+            total_regen_staked: Uint128::new(staked),
+            total_dregen_supply: Uint128::new(supply),
+            exchange_rate: Decimal::one(),
+            last_update_time: Timestamp::from_seconds(0),
+            total_rewards_claimed: Uint128::zero(),
+            pending_unbonding: Uint128::zero(),
+        };
+        let at = Timestamp::from_seconds;
+
+        save_state(&mut storage, &state(0, 0), at(1_000)).unwrap();
+        // Same rate within the interval: no new snapshot
+        save_state(&mut storage, &state(100, 100), at(2_000)).unwrap();
+        // Rate moves to 1.1, then 1.3
+        save_state(&mut storage, &state(110, 100), at(3_000)).unwrap();
+        save_state(&mut storage, &state(130, 100), at(5_000)).unwrap();
+        let times: Vec<u64> = RATE_SNAPSHOTS
+            .keys(&storage, None, None, Order::Ascending)
+            .collect::<StdResult<_>>()
+            .unwrap();
+        assert_eq!(times, vec![1_000, 3_000, 5_000]);
+
+        // 1.0 for 2_000s and 1.1 for 2_000s
+        assert_eq!(twap_rate(&storage, 1_000, 5_000).unwrap(), Decimal::permille(1_050));
+        // 1.1 for 1_000s and 1.3 for 1_000s, past the last snapshot
+        assert_eq!(twap_rate(&storage, 4_000, 6_000).unwrap(), Decimal::percent(120));
+        assert!(twap_rate(&storage, 500, 2_000).is_err());
+        assert!(twap_rate(&storage, 2_000, 2_000).is_err());
+
+        // An unchanged rate is re-recorded once the interval passes, keeping when it changed
+        let later = 5_000 + RATE_SNAPSHOT_INTERVAL;
+        save_state(&mut storage, &state(260, 200), at(later)).unwrap();
+        let last = latest_rate_snapshot(&storage, later).unwrap().unwrap();
+        assert_eq!((last.time, last.changed_at), (at(later), at(5_000)));
+    }
+
+    #[test]
+    fn derived_storage_follows_every_write() {
+        let mut storage = MockStorage::new();
+        let validator = |address: &str, weight: u64, delegated: u128, is_active: bool| {
+            ValidatorInfo {
+                address: address.to_string(),
+                delegated_amount: Uint128::new(delegated),
+                weight: Decimal::percent(weight),
+                last_reward_claim: Timestamp::from_seconds(0),
+                slashing_events: 0,
+                uptime_percentage: Decimal::one(),
+                commission_rate: Decimal::zero(),
+                is_active,
+                draining: !is_active,
+                tags: vec![],
+            }
+        };
+        let check = |storage: &MockStorage| {
+            let (computed, active) = scan_validators(storage).unwrap();
+            assert_eq!(VALIDATOR_TOTALS.load(storage).unwrap(), computed);
+            let cached: Vec<(String, Decimal)> = ACTIVE_VALIDATORS
+                .range(storage, None, None, Order::Ascending)
+                .collect::<StdResult<_>>()
+                .unwrap();
+            assert_eq!(cached, active);
+            computed
+        };
+
+        save_validator(&mut storage, &validator("a", 60, 100, true)).unwrap();
+        save_validator(&mut storage, &validator("b", 40, 0, true)).unwrap();
+        check(&storage);
+        // Stake moves and weight changes replace the previous contribution
+        save_validator(&mut storage, &validator("b", 50, 250, true)).unwrap();
+        // Deactivation keeps the stake in the totals but leaves the active set
+        save_validator(&mut storage, &validator("a", 60, 100, false)).unwrap();
+        let totals = check(&storage);
+        assert_eq!(totals.active_count, 1);
+        assert_eq!(totals.active_weight, Decimal::percent(50));
+        assert_eq!(totals.total_delegated, Uint128::new(350));
+
+        remove_validator(&mut storage, "a").unwrap();
+        remove_validator(&mut storage, "b").unwrap();
+        assert_eq!(check(&storage), ValidatorTotals::default());
+    }
+}
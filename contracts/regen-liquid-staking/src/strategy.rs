@@ -0,0 +1,602 @@
+use cosmwasm_std::{Decimal, StdError, StdResult, Uint128};
+
+use crate::state::{DelegationStrategyKind, StakeRouting, ValidatorInfo};
+
+/// Voting power share assumed for validators reporting zero, so none of them absorbs all stake
+const MIN_VOTING_POWER: Decimal = Decimal::raw(100_000_000_000_000); // 0.01%
+
+/// A validator new stake may be delegated to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Candidate {
+    pub address: String,
+    /// Target weight after the diversity constraints and any decentralization skew
+    pub weight: Decimal,
+    /// Stake the hub already delegates to the validator
+    pub delegated: Uint128,
+    /// Performance score, see `performance_score`
+    pub score: Decimal,
+    /// Share of network voting power, when reported by the registry oracle
+    pub voting_power: Option<Decimal>,
+    /// Position by voting power, 1 being the largest
+    pub rank: Option<u32>,
+    /// Most new stake the validator may receive
+    pub cap: Option<Uint128>,
+}
+
+/// How stake entering the hub is split across candidates. Candidates with zero weight never
+/// receive stake, and caps are respected as long as together they leave room for the amount.
+pub trait DelegationStrategy {
+    /// Relative share of new stake for each candidate, before caps
+    fn preferences(&self, candidates: &[Candidate]) -> StdResult<Vec<Decimal>>;
+
+    /// Split `amount` across `candidates`, returning non-zero `(address, amount)` pairs
+    fn allocate(
+        &self,
+        amount: Uint128,
+        candidates: &[Candidate],
+    ) -> StdResult<Vec<(String, Uint128)>> {
+        let mut preferences = self.preferences(candidates)?;
+        // Nothing preferred (e.g. every score is zero): fall back to the plain weights
+        if preferences.iter().all(|p| p.is_zero()) {
+            preferences = candidates.iter().map(|c| c.weight).collect();
+        }
+        let caps: Vec<Option<Uint128>> = candidates.iter().map(|c| c.cap).collect();
+        let amounts = fill_capped(amount, &preferences, &caps)?;
+        Ok(to_allocations(candidates, amounts))
+    }
+}
+
+/// The same amount to every candidate with a weight
+pub struct Equal;
+
+/// Proportional to weight
+pub struct Weighted;
+
+/// Proportional to weight scaled by performance score
+pub struct ScoreBased;
+
+/// Proportional to weight divided by network voting power share. Candidates without a
+/// reported share are treated as average; with no shares reported this equals `Weighted`.
+pub struct InverseVotingPower;
+
+/// Into the candidates furthest below their weighted share of the hub's stake, largest
+/// deficit first, so new stake also rebalances
+pub struct FillUnderweight;
+
+impl DelegationStrategy for Equal {
+    fn preferences(&self, candidates: &[Candidate]) -> StdResult<Vec<Decimal>> {
+        Ok(candidates
+            .iter()
+            .map(|c| if c.weight.is_zero() { Decimal::zero() } else { Decimal::one() })
+            .collect())
+    }
+}
+
+impl DelegationStrategy for Weighted {
+    fn preferences(&self, candidates: &[Candidate]) -> StdResult<Vec<Decimal>> {
+        Ok(candidates.iter().map(|c| c.weight).collect())
+    }
+}
+
+impl DelegationStrategy for ScoreBased {
+    fn preferences(&self, candidates: &[Candidate]) -> StdResult<Vec<Decimal>> {
+        candidates
+            .iter()
+            .map(|c| Ok(c.weight.checked_mul(c.score)?))
+            .collect()
+    }
+}
+
+impl DelegationStrategy for InverseVotingPower {
+    fn preferences(&self, candidates: &[Candidate]) -> StdResult<Vec<Decimal>> {
+        let reported: Vec<Decimal> = candidates.iter().filter_map(|c| c.voting_power).collect();
+        if reported.is_empty() {
+            return Weighted.preferences(candidates);
+        }
+        let average = reported
+            .iter()
+            .sum::<Decimal>()
+            .checked_div(Decimal::from_ratio(reported.len() as u128, 1u128))
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+        candidates
+            .iter()
+            .map(|c| {
+                let power = c.voting_power.unwrap_or(average).max(MIN_VOTING_POWER);
+                c.weight
+                    .checked_div(power)
+                    .map_err(|e| StdError::generic_err(e.to_string()))
+            })
+            .collect()
+    }
+}
+
+impl DelegationStrategy for FillUnderweight {
+    fn preferences(&self, candidates: &[Candidate]) -> StdResult<Vec<Decimal>> {
+        Weighted.preferences(candidates)
+    }
+
+    fn allocate(
+        &self,
+        amount: Uint128,
+        candidates: &[Candidate],
+    ) -> StdResult<Vec<(String, Uint128)>> {
+        let weights = self.preferences(candidates)?;
+        let delegated: Uint128 = candidates.iter().map(|c| c.delegated).sum();
+        let targets = split_by_weight(delegated.checked_add(amount)?, &weights)?;
+        let mut deficits: Vec<(usize, Uint128)> = candidates
+            .iter()
+            .zip(targets)
+            .enumerate()
+            .map(|(i, (c, target))| (i, target.saturating_sub(c.delegated)))
+            .filter(|(_, deficit)| !deficit.is_zero())
+            .collect();
+        deficits.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let mut amounts = vec![Uint128::zero(); candidates.len()];
+        let mut remaining = amount;
+        for (i, deficit) in deficits {
+            let room = candidates[i].cap.unwrap_or(Uint128::MAX);
+            let give = deficit.min(room).min(remaining);
+            amounts[i] = give;
+            remaining -= give;
+        }
+        // Only caps can leave stake over; place it by weight in the room that is left
+        if !remaining.is_zero() {
+            let caps: Vec<Option<Uint128>> = candidates
+                .iter()
+                .zip(&amounts)
+                .map(|(c, given)| c.cap.map(|cap| cap.saturating_sub(*given)))
+                .collect();
+            for (a, extra) in amounts.iter_mut().zip(fill_capped(remaining, &weights, &caps)?) {
+                *a = a.checked_add(extra)?;
+            }
+        }
+        Ok(to_allocations(candidates, amounts))
+    }
+}
+
+/// The strategy selected by `Config::delegation_strategy`
+pub fn strategy_for(kind: DelegationStrategyKind) -> &'static dyn DelegationStrategy {
+    match kind {
+        DelegationStrategyKind::Equal => &Equal,
+        DelegationStrategyKind::Weighted => &Weighted,
+        DelegationStrategyKind::ScoreBased => &ScoreBased,
+        DelegationStrategyKind::InverseVotingPower => &InverseVotingPower,
+        DelegationStrategyKind::FillUnderweight => &FillUnderweight,
+    }
+}
+
+/// Route a deposit to the few candidates furthest below the stake `strategy` would give them if
+/// the hub's whole stake were split anew, filling the largest shortfall first. At most
+/// `max_validators` receive stake and parts below `min_delegation` are folded into the largest,
+/// so gas stays flat as the set grows and repeated deposits converge on the strategy's targets.
+pub fn route_stake(
+    strategy: &dyn DelegationStrategy,
+    amount: Uint128,
+    candidates: &[Candidate],
+    routing: &StakeRouting,
+) -> StdResult<Vec<(String, Uint128)>> {
+    let delegated: Uint128 = candidates.iter().map(|c| c.delegated).sum();
+    // Caps bound new stake, so the whole-stake split may add them to what is held already
+    let whole = candidates
+        .iter()
+        .map(|c| {
+            Ok(Candidate {
+                cap: c.cap.map(|cap| cap.checked_add(c.delegated)).transpose()?,
+                ..c.clone()
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let targets = strategy.allocate(delegated.checked_add(amount)?, &whole)?;
+    let mut shortfalls: Vec<(String, Uint128)> = candidates
+        .iter()
+        .filter_map(|c| {
+            let target = targets.iter().find(|(a, _)| a == &c.address)?.1;
+            let shortfall = target.saturating_sub(c.delegated);
+            (!shortfall.is_zero()).then(|| (c.address.clone(), shortfall))
+        })
+        .collect();
+    shortfalls.sort_by(|a, b| b.1.cmp(&a.1));
+    shortfalls.truncate(routing.max_validators.max(1) as usize);
+    if shortfalls.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut routed = Vec::with_capacity(shortfalls.len());
+    let mut remaining = amount;
+    for (address, shortfall) in &shortfalls {
+        let part = (*shortfall).min(remaining);
+        remaining -= part;
+        routed.push((address.clone(), part));
+    }
+    // The chosen shortfalls can total less than the deposit; spread the rest in proportion
+    if !remaining.is_zero() {
+        let weights: Vec<Decimal> = shortfalls
+            .iter()
+            .map(|(_, shortfall)| Decimal::from_ratio(*shortfall, 1u128))
+            .collect();
+        for (r, extra) in routed.iter_mut().zip(split_by_weight(remaining, &weights)?) {
+            r.1 = r.1.checked_add(extra)?;
+        }
+    }
+    routed.retain(|(_, part)| !part.is_zero());
+    while routed.len() > 1 && routed[routed.len() - 1].1 < routing.min_delegation {
+        let (_, dust) = routed.pop().unwrap_or_default();
+        routed[0].1 = routed[0].1.checked_add(dust)?;
+    }
+    Ok(routed)
+}
+
+/// Pick the stake to undelegate: validators outside `candidates` (inactive or draining) first,
+/// largest holding first, then those furthest above the stake `strategy` gives them once the
+/// unbonding is done, so unbonding also rebalances. At most `max_validators` are touched unless
+/// they hold too little between them, in which case the largest remaining holdings make it up.
+pub fn route_unbond(
+    strategy: &dyn DelegationStrategy,
+    amount: Uint128,
+    candidates: &[Candidate],
+    inactive: &[(String, Uint128)], // (address, delegated amount)
+    max_validators: Option<u32>,
+) -> StdResult<Vec<(String, Uint128)>> {
+    let held = candidates
+        .iter()
+        .map(|c| c.delegated)
+        .chain(inactive.iter().map(|(_, delegated)| *delegated))
+        .sum::<Uint128>();
+    if amount > held {
+        return Err(StdError::generic_err(format!(
+            "Cannot undelegate {amount} with {held} delegated"
+        )));
+    }
+    // Targets once inactive validators are emptied; caps only bound new stake
+    let uncapped: Vec<Candidate> =
+        candidates.iter().map(|c| Candidate { cap: None, ..c.clone() }).collect();
+    let targets = if uncapped.iter().all(|c| c.weight.is_zero()) || held == amount {
+        vec![]
+    } else {
+        strategy.allocate(held - amount, &uncapped)?
+    };
+
+    let mut sources: Vec<(String, Uint128, Uint128)> = inactive
+        .iter()
+        .filter(|(_, delegated)| !delegated.is_zero())
+        .map(|(address, delegated)| (address.clone(), *delegated, *delegated))
+        .collect();
+    sources.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut overweight: Vec<(String, Uint128, Uint128)> = candidates
+        .iter()
+        .filter_map(|c| {
+            let target = targets
+                .iter()
+                .find(|(a, _)| a == &c.address)
+                .map_or(Uint128::zero(), |(_, t)| *t);
+            let surplus = c.delegated.saturating_sub(target);
+            (!surplus.is_zero()).then(|| (c.address.clone(), surplus, c.delegated))
+        })
+        .collect();
+    overweight.sort_by(|a, b| b.1.cmp(&a.1));
+    sources.extend(overweight);
+    sources.truncate(max_validators.map_or(usize::MAX, |m| m.max(1) as usize));
+
+    // (address, undelegated, still available)
+    let mut routed: Vec<(String, Uint128, Uint128)> = Vec::with_capacity(sources.len());
+    let mut remaining = amount;
+    for (address, excess, delegated) in sources {
+        let part = excess.min(remaining);
+        remaining -= part;
+        routed.push((address, part, delegated - part));
+    }
+    // Surpluses of the chosen validators fall short: take more of their stake first, then
+    // reach for the largest other holdings
+    for r in routed.iter_mut() {
+        let part = r.2.min(remaining);
+        r.1 += part;
+        remaining -= part;
+    }
+    if !remaining.is_zero() {
+        let mut others: Vec<(String, Uint128)> = candidates
+            .iter()
+            .map(|c| (c.address.clone(), c.delegated))
+            .chain(inactive.iter().cloned())
+            .filter(|(address, _)| !routed.iter().any(|r| &r.0 == address))
+            .collect();
+        others.sort_by(|a, b| b.1.cmp(&a.1));
+        for (address, delegated) in others {
+            if remaining.is_zero() {
+                break;
+            }
+            let part = delegated.min(remaining);
+            remaining -= part;
+            routed.push((address, part, delegated - part));
+        }
+    }
+    Ok(routed
+        .into_iter()
+        .filter(|(_, part, _)| !part.is_zero())
+        .map(|(address, part, _)| (address, part))
+        .collect())
+}
+
+/// Uptime times the share of rewards left after commission, halved by each slashing event
+pub fn performance_score(v: &ValidatorInfo) -> StdResult<Decimal> {
+    let kept = Decimal::one().saturating_sub(v.commission_rate);
+    let slashing = Decimal::from_ratio(1u128 << v.slashing_events.min(64), 1u128);
+    v.uptime_percentage
+        .checked_mul(kept)?
+        .checked_div(slashing)
+        .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+/// Split `amount` by `preferences`, filling candidates whose portion would exceed their cap to
+/// the cap and splitting the rest again among the others. Stake no candidate has room for is
+/// spread by preference regardless of caps rather than refused.
+pub fn fill_capped(
+    amount: Uint128,
+    preferences: &[Decimal],
+    caps: &[Option<Uint128>],
+) -> StdResult<Vec<Uint128>> {
+    let mut amounts = vec![Uint128::zero(); preferences.len()];
+    let mut open: Vec<usize> = (0..preferences.len())
+        .filter(|i| !preferences[*i].is_zero() && caps[*i] != Some(Uint128::zero()))
+        .collect();
+    let mut remaining = amount;
+    while !remaining.is_zero() && !open.is_empty() {
+        let open_preferences: Vec<Decimal> = open.iter().map(|i| preferences[*i]).collect();
+        let split = split_by_weight(remaining, &open_preferences)?;
+        let full: Vec<usize> = open
+            .iter()
+            .zip(&split)
+            .filter(|(i, part)| caps[**i].map_or(false, |cap| **part > cap))
+            .map(|(i, _)| *i)
+            .collect();
+        if full.is_empty() {
+            for (i, part) in open.iter().zip(split) {
+                amounts[*i] = part;
+            }
+            remaining = Uint128::zero();
+            break;
+        }
+        for i in &full {
+            let cap = caps[*i].unwrap_or_default();
+            amounts[*i] = cap;
+            remaining = remaining.checked_sub(cap)?;
+        }
+        open.retain(|i| !full.contains(i));
+    }
+    if !remaining.is_zero() {
+        for (a, extra) in amounts.iter_mut().zip(split_by_weight(remaining, preferences)?) {
+            *a = a.checked_add(extra)?;
+        }
+    }
+    Ok(amounts)
+}
+
+/// Split `amount` proportionally to `weights`; the last non-zero weight takes the rounding
+fn split_by_weight(amount: Uint128, weights: &[Decimal]) -> StdResult<Vec<Uint128>> {
+    let total: Decimal = weights.iter().sum();
+    if total.is_zero() {
+        return Err(StdError::generic_err("Total validator weight cannot be zero"));
+    }
+    let last = weights.iter().rposition(|w| !w.is_zero()).unwrap_or_default();
+    let mut left = amount;
+    let mut split = Vec::with_capacity(weights.len());
+    for (i, weight) in weights.iter().enumerate() {
+        let part = if i == last {
+            left
+        } else {
+            weight
+                .checked_div(total)
+                .map_err(|e| StdError::generic_err(e.to_string()))?
+                .checked_mul(Decimal::from_ratio(amount, 1u128))?
+                .to_uint_floor()
+        };
+        left = left.checked_sub(part)?;
+        split.push(part);
+    }
+    Ok(split)
+}
+
+fn to_allocations(candidates: &[Candidate], amounts: Vec<Uint128>) -> Vec<(String, Uint128)> {
+    candidates
+        .iter()
+        .zip(amounts)
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(c, amount)| (c.address.clone(), amount))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [DelegationStrategyKind; 5] = [
+        DelegationStrategyKind::Equal,
+        DelegationStrategyKind::Weighted,
+        DelegationStrategyKind::ScoreBased,
+        DelegationStrategyKind::InverseVotingPower,
+        DelegationStrategyKind::FillUnderweight,
+    ];
+
+    fn candidate(address: &str, weight: u64, delegated: u128) -> Candidate {
+        Candidate {
+            address: address.to_string(),
+            weight: Decimal::percent(weight),
+            delegated: Uint128::new(delegated),
+            score: Decimal::one(),
+            voting_power: None,
+            rank: None,
+            cap: None,
+        }
+    }
+
+    fn fixtures() -> Vec<Vec<Candidate>> {
+        let mut scored = vec![candidate("a", 50, 0), candidate("b", 30, 0), candidate("c", 20, 0)];
+        scored[0].score = Decimal::percent(40);
+        scored[1].voting_power = Some(Decimal::percent(8));
+        scored[2].voting_power = Some(Decimal::zero());
+
+        let mut capped = vec![
+            candidate("a", 60, 5_000),
+            candidate("b", 20, 0),
+            candidate("c", 20, 100),
+        ];
+        capped[0].cap = Some(Uint128::new(100));
+        capped[1].cap = Some(Uint128::new(4_000));
+        capped[0].voting_power = Some(Decimal::percent(1));
+
+        vec![
+            vec![candidate("a", 100, 0)],
+            scored,
+            capped,
+            // A zero-weight validator and an uneven split that needs rounding
+            vec![candidate("a", 33, 7), candidate("b", 0, 0), candidate("c", 67, 1_000)],
+        ]
+    }
+
+    #[test]
+    fn strategies_respect_shared_invariants() {
+        for kind in ALL {
+            for candidates in fixtures() {
+                for amount in [1u128, 997, 10_000] {
+                    let amount = Uint128::new(amount);
+                    let allocations = strategy_for(kind).allocate(amount, &candidates).unwrap();
+                    let context = format!("{kind:?} {amount} {candidates:?}");
+
+                    // Conservation: exactly the amount is delegated, in positive parts
+                    let total: Uint128 = allocations.iter().map(|(_, a)| *a).sum();
+                    assert_eq!(total, amount, "{context}");
+                    assert!(allocations.iter().all(|(_, a)| !a.is_zero()), "{context}");
+
+                    let caps_room: Uint128 =
+                        candidates.iter().map(|c| c.cap.unwrap_or(amount)).sum();
+                    for (address, allocated) in &allocations {
+                        let c = candidates.iter().find(|c| &c.address == address).unwrap();
+                        assert!(!c.weight.is_zero(), "{context}");
+                        if caps_room >= amount {
+                            assert!(c.cap.map_or(true, |cap| *allocated <= cap), "{context}");
+                        }
+                    }
+                    let mut addresses: Vec<_> = allocations.iter().map(|(a, _)| a).collect();
+                    addresses.dedup();
+                    assert_eq!(addresses.len(), allocations.len(), "{context}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strategies_differ_as_documented() {
+        let mut candidates =
+            vec![candidate("a", 50, 3_000), candidate("b", 30, 0), candidate("c", 20, 0)];
+        candidates[0].score = Decimal::percent(40);
+        candidates[0].voting_power = Some(Decimal::percent(10));
+        candidates[1].voting_power = Some(Decimal::percent(5));
+        candidates[2].voting_power = Some(Decimal::percent(1));
+        let allocate = |kind| -> Vec<u128> {
+            let allocations = strategy_for(kind).allocate(Uint128::new(1_000), &candidates);
+            let allocations = allocations.unwrap();
+            ["a", "b", "c"]
+                .iter()
+                .map(|a| {
+                    allocations
+                        .iter()
+                        .find(|(addr, _)| addr == a)
+                        .map_or(0, |(_, amount)| amount.u128())
+                })
+                .collect()
+        };
+
+        assert_eq!(allocate(DelegationStrategyKind::Equal), vec![333, 333, 334]);
+        assert_eq!(allocate(DelegationStrategyKind::Weighted), vec![500, 300, 200]);
+        // Scores 0.4, 1, 1 scale the weights to 20:30:20
+        assert_eq!(allocate(DelegationStrategyKind::ScoreBased), vec![285, 428, 287]);
+        // Weight over voting power is 5:6:20
+        assert_eq!(allocate(DelegationStrategyKind::InverseVotingPower), vec![161, 193, 646]);
+        // Of 4_000 total, `a` already holds more than its 2_000; `b` is 1_200 short of its share
+        // and takes everything before `c` is considered
+        assert_eq!(allocate(DelegationStrategyKind::FillUnderweight), vec![0, 1_000, 0]);
+    }
+
+    #[test]
+    fn routed_stake_is_bounded_and_converges() {
+        let routing = StakeRouting {
+            max_validators: 2,
+            min_delegation: Uint128::new(10),
+        };
+        let mut candidates: Vec<Candidate> =
+            ["a", "b", "c", "d", "e"].iter().map(|a| candidate(a, 20, 0)).collect();
+        candidates[0].delegated = Uint128::new(1_000);
+
+        for _ in 0..40 {
+            let routed =
+                route_stake(&Weighted, Uint128::new(100), &candidates, &routing).unwrap();
+            assert!(!routed.is_empty() && routed.len() <= 2);
+            assert_eq!(routed.iter().map(|(_, a)| *a).sum::<Uint128>(), Uint128::new(100));
+            for (address, amount) in routed {
+                assert!(amount >= routing.min_delegation);
+                let c = candidates.iter_mut().find(|c| c.address == address).unwrap();
+                c.delegated += amount;
+            }
+        }
+        // 5_000 in total; `a` started at its target and never received more, and the others
+        // end up within the folded dust of theirs
+        assert_eq!(candidates[0].delegated, Uint128::new(1_000));
+        let target = Uint128::new(1_000);
+        assert!(candidates.iter().all(|c| c.delegated.abs_diff(target) <= routing.min_delegation));
+
+        // 18 in total wants 9 each: 9 and 6 short, but 6 is dust and joins the larger part
+        let pair = vec![candidate("a", 50, 0), candidate("b", 50, 3)];
+        assert_eq!(
+            route_stake(&Weighted, Uint128::new(15), &pair, &routing).unwrap(),
+            vec![("a".to_string(), Uint128::new(15))]
+        );
+    }
+
+    #[test]
+    fn unbonding_drains_inactive_and_overweight_first() {
+        let candidates = vec![
+            candidate("a", 25, 4_000),
+            candidate("b", 25, 2_000),
+            candidate("c", 50, 2_000),
+        ];
+        let inactive = vec![("x".to_string(), Uint128::new(500))];
+        let unbond = |amount: u128, max: Option<u32>| {
+            route_unbond(&Weighted, Uint128::new(amount), &candidates, &inactive, max).unwrap()
+        };
+        let parts = |parts: &[(&str, u128)]| -> Vec<(String, Uint128)> {
+            parts.iter().map(|(a, amount)| (a.to_string(), Uint128::new(*amount))).collect()
+        };
+
+        // The inactive validator goes first, then `a`, furthest above 25% of what remains
+        assert_eq!(unbond(300, None), parts(&[("x", 300)]));
+        assert_eq!(unbond(2_500, None), parts(&[("x", 500), ("a", 2_000)]));
+        // Limited to one validator, the largest other holding makes up the rest
+        assert_eq!(unbond(2_500, Some(1)), parts(&[("x", 500), ("a", 2_000)]));
+        // `a` is 3_000 over its 1_000 target and gives more of its stake before `b` is touched
+        assert_eq!(unbond(4_500, Some(2)), parts(&[("x", 500), ("a", 4_000)]));
+        // Everything can be unbonded, but not more
+        let all: Uint128 = unbond(8_500, Some(1)).iter().map(|(_, a)| *a).sum();
+        assert_eq!(all, Uint128::new(8_500));
+        let too_much = route_unbond(&Weighted, Uint128::new(8_501), &candidates, &inactive, None);
+        assert!(too_much.is_err());
+    }
+
+    #[test]
+    fn score_penalizes_commission_and_slashing() {
+        let v = |commission: u64, uptime: u64, slashing_events: u32| ValidatorInfo {
+            address: "v".to_string(),
+            delegated_amount: Uint128::zero(),
+            weight: Decimal::one(),
+            last_reward_claim: cosmwasm_std::Timestamp::from_seconds(0),
+            slashing_events,
+            uptime_percentage: Decimal::percent(uptime),
+            commission_rate: Decimal::percent(commission),
+            is_active: true,
+            draining: false,
+            tags: vec![],
+        };
+        assert_eq!(performance_score(&v(0, 100, 0)).unwrap(), Decimal::one());
+        assert_eq!(performance_score(&v(10, 90, 0)).unwrap(), Decimal::percent(81));
+        assert_eq!(performance_score(&v(10, 90, 1)).unwrap(), Decimal::permille(405));
+    }
+}
@@ -0,0 +1,216 @@
+use cosmwasm_std::{
+    BankMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult,
+};
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::consumers::consumer_update_msgs;
+use crate::helpers::{new_validator_info, validate_validator};
+use crate::history::record_snapshot;
+use crate::profiles::init_profile;
+use crate::msg::{ApplicationParams, ApplicationResponse, ApplicationsResponse};
+use crate::state::{Application, ApplicationRules, APPLICATIONS, APPLICATION_RULES, CONFIG, VALIDATORS};
+
+pub fn exec_submit_application(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    application: ApplicationParams,
+) -> Result<Response, ContractError> {
+    let address = application.address;
+    if VALIDATORS.has(deps.storage, address.clone()) {
+        return Err(ContractError::Std(StdError::generic_err("validator already exists")));
+    }
+    if APPLICATIONS.has(deps.storage, address.clone()) {
+        return Err(ContractError::Std(StdError::generic_err("application already pending")));
+    }
+
+    let rules = APPLICATION_RULES.load(deps.storage)?;
+    let chain_commission = check_eligibility(deps.as_ref(), &rules, &address)?;
+    if application.commission_commitment < chain_commission
+        || application.commission_commitment > rules.max_commission
+    {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "commission commitment must be between {} and {}",
+            chain_commission, rules.max_commission
+        ))));
+    }
+
+    match &rules.deposit {
+        Some(deposit) if info.funds != [deposit.clone()] => {
+            return Err(ContractError::Std(StdError::generic_err(format!(
+                "application requires a deposit of {}",
+                deposit
+            ))));
+        }
+        None if !info.funds.is_empty() => {
+            return Err(ContractError::Std(StdError::generic_err("no deposit is required")));
+        }
+        _ => {}
+    }
+
+    APPLICATIONS.save(
+        deps.storage,
+        address.clone(),
+        &Application {
+            applicant: info.sender.clone(),
+            address: address.clone(),
+            moniker: application.moniker,
+            website: application.website,
+            details: application.details,
+            commission_commitment: application.commission_commitment,
+            deposit: info.funds,
+            submitted_at: env.block.time,
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("action", "submit_application")
+        .add_attribute("validator", address)
+        .add_attribute("applicant", info.sender))
+}
+
+pub fn exec_withdraw_application(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    let app = load_application(deps.as_ref(), &address)?;
+    if app.applicant != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    APPLICATIONS.remove(deps.storage, address.clone());
+    Ok(refund_deposit(Response::new(), &app)
+        .add_attribute("action", "withdraw_application")
+        .add_attribute("validator", address))
+}
+
+pub fn exec_approve_application(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    weight: Decimal,
+) -> Result<Response, ContractError> {
+    ensure_reviewer(deps.as_ref(), &info)?;
+    let app = load_application(deps.as_ref(), &address)?;
+    if VALIDATORS.has(deps.storage, address.clone()) {
+        return Err(ContractError::Std(StdError::generic_err("validator already exists")));
+    }
+    // The validator may have changed since applying; the rules must still hold
+    let rules = APPLICATION_RULES.load(deps.storage)?;
+    let commission_rate = check_eligibility(deps.as_ref(), &rules, &address)?;
+
+    let v = new_validator_info(address.clone(), weight, commission_rate, env.block.time);
+    VALIDATORS.save(deps.storage, address.clone(), &v)?;
+    record_snapshot(deps.storage, &v, env.block.time)?;
+    init_profile(
+        deps.storage,
+        &address,
+        app.applicant.clone(),
+        app.moniker.clone(),
+        app.website.clone(),
+        app.details.clone(),
+        env.block.time,
+    )?;
+    APPLICATIONS.remove(deps.storage, address.clone());
+    Ok(refund_deposit(Response::new(), &app)
+        .add_submessages(consumer_update_msgs(deps)?)
+        .add_attribute("action", "approve_application")
+        .add_attribute("validator", address)
+        .add_attribute("weight", weight.to_string()))
+}
+
+pub fn exec_reject_application(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    reason: String,
+) -> Result<Response, ContractError> {
+    ensure_reviewer(deps.as_ref(), &info)?;
+    let app = load_application(deps.as_ref(), &address)?;
+    APPLICATIONS.remove(deps.storage, address.clone());
+    Ok(refund_deposit(Response::new(), &app)
+        .add_attribute("action", "reject_application")
+        .add_attribute("validator", address)
+        .add_attribute("reason", reason))
+}
+
+pub fn exec_update_application_rules(
+    deps: DepsMut,
+    rules: ApplicationRules,
+) -> Result<Response, ContractError> {
+    if rules.max_commission > Decimal::one() || rules.max_commission_change_rate > Decimal::one() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "commission limits cannot exceed 100%",
+        )));
+    }
+    APPLICATION_RULES.save(deps.storage, &rules)?;
+    Ok(Response::new().add_attribute("action", "update_application_rules"))
+}
+
+/// Check the validator is bonded on chain within the commission limits, returning its commission
+fn check_eligibility(
+    deps: Deps,
+    rules: &ApplicationRules,
+    address: &str,
+) -> Result<Decimal, ContractError> {
+    let validator = validate_validator(deps, address)?;
+    if validator.commission > rules.max_commission {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "commission {} exceeds maximum {}",
+            validator.commission, rules.max_commission
+        ))));
+    }
+    if validator.max_change_rate > rules.max_commission_change_rate {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "commission change rate {} exceeds maximum {}",
+            validator.max_change_rate, rules.max_commission_change_rate
+        ))));
+    }
+    Ok(validator.commission)
+}
+
+fn ensure_reviewer(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin && cfg.governance.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+fn load_application(deps: Deps, address: &str) -> StdResult<Application> {
+    APPLICATIONS
+        .may_load(deps.storage, address.to_string())?
+        .ok_or_else(|| StdError::not_found("Application"))
+}
+
+fn refund_deposit(res: Response, app: &Application) -> Response {
+    if app.deposit.is_empty() {
+        return res;
+    }
+    res.add_message(BankMsg::Send {
+        to_address: app.applicant.to_string(),
+        amount: app.deposit.clone(),
+    })
+}
+
+pub fn query_application(deps: Deps, address: String) -> StdResult<ApplicationResponse> {
+    Ok(ApplicationResponse {
+        application: APPLICATIONS.may_load(deps.storage, address)?,
+    })
+}
+
+pub fn query_applications(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ApplicationsResponse> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let applications = APPLICATIONS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| r.map(|(_, v)| v))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ApplicationsResponse { applications })
+}
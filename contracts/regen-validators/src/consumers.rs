@@ -0,0 +1,279 @@
+use cosmwasm_std::{
+    to_binary, Addr, Decimal, Deps, DepsMut, Env, Order, Response, StdError, StdResult, Storage,
+    SubMsg, SubMsgResult, WasmMsg,
+};
+use regen_types::{ActiveValidatorsResponse, HubExecuteMsg, ValidatorParams};
+
+use crate::error::ContractError;
+use crate::helpers::{active_validator_params, delete_validator};
+use crate::stake::clear_consumer_stake;
+use crate::msg::{ConsumerResponse, ConsumersResponse, WeightOverride};
+use crate::state::{
+    Consumer, PushFailure, CONSUMERS, CONSUMER_WEIGHTS, PENDING_DRAINS, PUSH_FAILURES,
+    PUSH_TARGETS, VALIDATORS,
+};
+
+/// Reply ids of consumer pushes are this base plus the push's position in the response; the
+/// consumer it went to is recorded in `PUSH_TARGETS`
+pub const CONSUMER_UPDATE_REPLY_BASE: u64 = 1_000_000;
+
+/// A consumer's view of the shared active set: restricted to its validators, with its
+/// weight overrides applied
+pub fn consumer_view(
+    deps: Deps,
+    address: &Addr,
+    consumer: &Consumer,
+) -> StdResult<Vec<ValidatorParams>> {
+    active_validator_params(deps)?
+        .into_iter()
+        .filter(|v| {
+            consumer
+                .validators
+                .as_ref()
+                .map_or(true, |set| set.contains(&v.address))
+        })
+        .map(|v| {
+            let weight = CONSUMER_WEIGHTS
+                .may_load(deps.storage, (address, &v.address))?
+                .unwrap_or(v.weight);
+            Ok(ValidatorParams {
+                address: v.address,
+                weight,
+            })
+        })
+        .collect()
+}
+
+/// Push `consumer` its view of the active set. The push is a `reply_on_error` submessage, so a
+/// consumer that rejects it is recorded in `PUSH_FAILURES` instead of failing the registry
+/// operation.
+fn consumer_update_msg(
+    deps: DepsMut,
+    index: usize,
+    address: &Addr,
+    consumer: &Consumer,
+) -> StdResult<SubMsg> {
+    let msg = HubExecuteMsg::RegistryUpdate {
+        validators: consumer_view(deps.as_ref(), address, consumer)?,
+    };
+    PUSH_TARGETS.save(deps.storage, index as u64, address)?;
+    Ok(SubMsg::reply_on_error(
+        WasmMsg::Execute {
+            contract_addr: address.to_string(),
+            msg: to_binary(&msg)?,
+            funds: vec![],
+        },
+        CONSUMER_UPDATE_REPLY_BASE + index as u64,
+    ))
+}
+
+/// Messages pushing each consumer its current view of the active set
+pub fn consumer_update_msgs(mut deps: DepsMut) -> StdResult<Vec<SubMsg>> {
+    let consumers = CONSUMERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    consumers
+        .iter()
+        .enumerate()
+        .map(|(index, (address, consumer))| {
+            consumer_update_msg(deps.branch(), index, address, consumer)
+        })
+        .collect()
+}
+
+/// Record a consumer that rejected its push; the registry operation itself goes through
+pub fn handle_consumer_update_reply(
+    deps: DepsMut,
+    env: Env,
+    index: u64,
+    result: SubMsgResult,
+) -> Result<Response, ContractError> {
+    let error = result.into_result().err().unwrap_or_default();
+    let consumer = PUSH_TARGETS.load(deps.storage, index)?;
+    PUSH_FAILURES.save(
+        deps.storage,
+        &consumer,
+        &PushFailure {
+            error: error.clone(),
+            time: env.block.time,
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("action", "consumer_update_failed")
+        .add_attribute("consumer", consumer)
+        .add_attribute("error", error))
+}
+
+/// Consumers whose view currently includes `validator`
+pub fn consumers_serving(deps: Deps, validator: &str) -> StdResult<Vec<Addr>> {
+    let mut serving = Vec::new();
+    for r in CONSUMERS.range(deps.storage, None, None, Order::Ascending) {
+        let (address, consumer) = r?;
+        if consumer_view(deps, &address, &consumer)?
+            .iter()
+            .any(|v| v.address == validator)
+        {
+            serving.push(address);
+        }
+    }
+    Ok(serving)
+}
+
+pub fn has_pending_drains(storage: &dyn Storage, validator: &str) -> bool {
+    PENDING_DRAINS
+        .prefix(validator)
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+pub fn clear_pending_drains(storage: &mut dyn Storage, validator: &str) -> StdResult<()> {
+    let consumers = PENDING_DRAINS
+        .prefix(validator)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for consumer in consumers {
+        PENDING_DRAINS.remove(storage, (validator, &consumer));
+    }
+    Ok(())
+}
+
+pub fn exec_set_consumer(
+    deps: DepsMut,
+    address: String,
+    name: String,
+    validators: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    let address = deps.api.addr_validate(&address)?;
+    for v in validators.iter().flatten() {
+        if !VALIDATORS.has(deps.storage, v.clone()) {
+            return Err(ContractError::Std(StdError::not_found("ValidatorInfo")));
+        }
+    }
+    let consumer = Consumer { name, validators };
+    CONSUMERS.save(deps.storage, &address, &consumer)?;
+    Ok(Response::new()
+        .add_submessage(consumer_update_msg(deps, 0, &address, &consumer)?)
+        .add_attribute("action", "set_consumer")
+        .add_attribute("consumer", address)
+        .add_attribute("name", consumer.name))
+}
+
+pub fn exec_remove_consumer(
+    deps: DepsMut,
+    env: Env,
+    address: String,
+) -> Result<Response, ContractError> {
+    let address = deps.api.addr_validate(&address)?;
+    if !CONSUMERS.has(deps.storage, &address) {
+        return Err(ContractError::Std(StdError::not_found("Consumer")));
+    }
+    CONSUMERS.remove(deps.storage, &address);
+    PUSH_FAILURES.remove(deps.storage, &address);
+    let overridden = CONSUMER_WEIGHTS
+        .prefix(&address)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for validator in overridden {
+        CONSUMER_WEIGHTS.remove(deps.storage, (&address, &validator));
+    }
+    clear_consumer_stake(deps.storage, &address, env.block.time)?;
+
+    // The removed consumer will not report drains anymore; finish removals only it was holding up
+    let waiting_on = PENDING_DRAINS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|r| match r {
+            Ok(((validator, consumer), _)) if consumer == address => Some(Ok(validator)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut removed = 0u32;
+    for validator in waiting_on {
+        PENDING_DRAINS.remove(deps.storage, (&validator, &address));
+        if !has_pending_drains(deps.storage, &validator) {
+            delete_validator(deps.storage, &validator)?;
+            removed += 1;
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_consumer")
+        .add_attribute("consumer", address)
+        .add_attribute("removed_validators", removed.to_string()))
+}
+
+pub fn exec_set_consumer_weight(
+    deps: DepsMut,
+    consumer: String,
+    validator: String,
+    weight: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let address = deps.api.addr_validate(&consumer)?;
+    let Some(c) = CONSUMERS.may_load(deps.storage, &address)? else {
+        return Err(ContractError::Std(StdError::not_found("Consumer")));
+    };
+    if !VALIDATORS.has(deps.storage, validator.clone()) {
+        return Err(ContractError::Std(StdError::not_found("ValidatorInfo")));
+    }
+    match weight {
+        Some(w) => CONSUMER_WEIGHTS.save(deps.storage, (&address, &validator), &w)?,
+        None => CONSUMER_WEIGHTS.remove(deps.storage, (&address, &validator)),
+    }
+    Ok(Response::new()
+        .add_submessage(consumer_update_msg(deps, 0, &address, &c)?)
+        .add_attribute("action", "set_consumer_weight")
+        .add_attribute("consumer", address)
+        .add_attribute("validator", validator)
+        .add_attribute(
+            "weight",
+            weight.map_or_else(|| "default".to_string(), |w| w.to_string()),
+        ))
+}
+
+fn consumer_response(
+    deps: Deps,
+    address: Addr,
+    consumer: Consumer,
+) -> StdResult<ConsumerResponse> {
+    let weight_overrides = CONSUMER_WEIGHTS
+        .prefix(&address)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| r.map(|(validator, weight)| WeightOverride { validator, weight }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ConsumerResponse {
+        address: address.to_string(),
+        name: consumer.name,
+        validators: consumer.validators,
+        weight_overrides,
+        last_push_failure: PUSH_FAILURES.may_load(deps.storage, &address)?,
+    })
+}
+
+pub fn query_consumers(deps: Deps) -> StdResult<ConsumersResponse> {
+    let consumers = CONSUMERS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| {
+            let (address, consumer) = r?;
+            consumer_response(deps, address, consumer)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ConsumersResponse { consumers })
+}
+
+pub fn query_consumer(deps: Deps, address: String) -> StdResult<ConsumerResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let consumer = CONSUMERS.load(deps.storage, &address)?;
+    consumer_response(deps, address, consumer)
+}
+
+pub fn query_consumer_validators(
+    deps: Deps,
+    consumer: String,
+) -> StdResult<ActiveValidatorsResponse> {
+    let address = deps.api.addr_validate(&consumer)?;
+    let c = CONSUMERS.load(deps.storage, &address)?;
+    Ok(ActiveValidatorsResponse {
+        validators: consumer_view(deps, &address, &c)?,
+    })
+}
@@ -1,15 +1,53 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response,
-    StdError, StdResult, Uint128,
+    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, Storage,
 };
 use cw2::set_contract_version;
+use regen_types::ActiveValidatorsResponse;
 
+use crate::consumers::{
+    clear_pending_drains, consumer_update_msgs, consumers_serving, exec_remove_consumer,
+    exec_set_consumer, exec_set_consumer_weight, handle_consumer_update_reply, has_pending_drains,
+    query_consumer, query_consumer_validators, query_consumers, CONSUMER_UPDATE_REPLY_BASE,
+};
+use crate::error::ContractError;
+use crate::applications::{
+    exec_approve_application, exec_reject_application, exec_submit_application,
+    exec_update_application_rules, exec_withdraw_application, query_application,
+    query_applications,
+};
 use crate::msg::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, QueryMsg, ValidatorParams, ValidatorResponse,
-    ValidatorsResponse,
+    ConfigResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ScoreBreakdownResponse,
+    ValidatorOrder, ValidatorParams, ValidatorResponse, ValidatorScore, ValidatorsResponse,
+};
+use crate::helpers::{
+    active_validator_params, bonded_validators, delete_validator, ensure_admin, new_validator_info,
+    validate_validator,
+};
+use crate::oracle::{
+    exec_submit_metrics, is_stale, query_metrics_submissions, query_validator_metrics,
+    query_voting_power,
+};
+use crate::history::{exec_prune_history, query_validator_history, record_snapshot};
+use crate::policy::{exec_evaluate_policy, exec_update_policy, query_policy_report};
+use crate::profiles::{
+    exec_set_profile_manager, exec_set_profile_verified, exec_update_profile, query_profile,
+    query_profiles,
+};
+use crate::stake::{exec_delegation_report, query_stake_summary, query_validator_stake};
+use crate::weights::{
+    exec_advance_weights, exec_set_weights, exec_update_weight_limits, move_weight, query_weight_schedule,
+};
+use crate::scoring::{assign_weights, score_validator};
+use crate::state::{
+    Config, Consumer, ScoringParams, ValidatorInfo, ValidatorStatus, APPLICATION_RULES, CONFIG,
+    CONSUMERS, HISTORY_PARAMS, NETWORK_STATS, ORACLE_PARAMS, PENDING_DRAINS, POLICY,
+    POLICY_VIOLATIONS, PROFILES, SCORING, VALIDATORS, VALIDATOR_METRICS, WEIGHT_LIMITS,
 };
-use crate::state::{Config, ValidatorInfo, CONFIG, VALIDATORS};
 use cosmwasm_std::Decimal;
+use cw_storage_plus::{Bound, Item};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 const CONTRACT_NAME: &str = "crates.io:regen-validators";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -20,26 +58,44 @@ pub fn instantiate(
     env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> Result<Response, StdError> {
+) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let admin: Addr = deps.api.addr_validate(&msg.admin)?;
-    CONFIG.save(deps.storage, &Config { admin: admin.clone() })?;
+    let oracle = msg.oracle.map(|o| deps.api.addr_validate(&o)).transpose()?;
+    let governance = msg.governance.map(|g| deps.api.addr_validate(&g)).transpose()?;
+    let attester = msg.attester.map(|a| deps.api.addr_validate(&a)).transpose()?;
+    CONFIG.save(
+        deps.storage,
+        &Config { admin: admin.clone(), oracle, governance, attester },
+    )?;
+    SCORING.save(deps.storage, &msg.scoring.unwrap_or_default())?;
+    ORACLE_PARAMS.save(deps.storage, &msg.oracle_params.unwrap_or_default())?;
+    APPLICATION_RULES.save(deps.storage, &msg.application_rules.unwrap_or_default())?;
+    POLICY.save(deps.storage, &msg.policy.unwrap_or_default())?;
+    HISTORY_PARAMS.save(deps.storage, &msg.history_params.unwrap_or_default())?;
+    WEIGHT_LIMITS.save(deps.storage, &msg.weight_limits.unwrap_or_default())?;
 
     // Seed initial validators
     for vp in msg.validators {
-        let _ = deps.api.addr_validate(&vp.address)?;
-        let v = ValidatorInfo {
-            address: vp.address.clone(),
-            delegated_amount: Uint128::zero(),
-            weight: vp.weight,
-            last_reward_claim: env.block.time,
-            slashing_events: 0,
-            uptime_percentage: Decimal::percent(100),
-            commission_rate: vp.commission_rate,
-            is_active: true,
-        };
+        validate_validator(deps.as_ref(), &vp.address)?;
+        let v = new_validator_info(vp.address.clone(), vp.weight, vp.commission_rate, env.block.time);
         VALIDATORS.save(deps.storage, vp.address.clone(), &v)?;
+        record_snapshot(deps.storage, &v, env.block.time)?;
+    }
+
+    for cp in msg.consumers {
+        let address = deps.api.addr_validate(&cp.address)?;
+        for v in cp.validators.iter().flatten() {
+            if !VALIDATORS.has(deps.storage, v.clone()) {
+                return Err(ContractError::Std(StdError::not_found("ValidatorInfo")));
+            }
+        }
+        CONSUMERS.save(
+            deps.storage,
+            &address,
+            &Consumer { name: cp.name, validators: cp.validators },
+        )?;
     }
 
     Ok(Response::new()
@@ -50,105 +106,332 @@ pub fn instantiate(
 }
 
 #[entry_point]
-pub fn execute(deps: DepsMut, _env: Env, info: MessageInfo, msg: ExecuteMsg) -> Result<Response, StdError> {
-    let cfg = CONFIG.load(deps.storage)?;
-    if info.sender != cfg.admin {
-        return Err(StdError::generic_err("unauthorized"));
-    }
-
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::AddValidator { params } => exec_add_validator(deps, params),
-        ExecuteMsg::RemoveValidator { address } => exec_remove_validator(deps, address),
-        ExecuteMsg::UpdateWeight { address, weight } => exec_update_weight(deps, address, weight),
+        ExecuteMsg::AddValidator { params } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_add_validator(deps, env, params)
+        }
+        ExecuteMsg::RemoveValidator { address } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_remove_validator(deps, env, address)
+        }
+        ExecuteMsg::UpdateWeight { address, weight } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_update_weight(deps, env, address, weight)
+        }
         ExecuteMsg::SetCommission { address, commission_rate } => {
-            exec_set_commission(deps, address, commission_rate)
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_commission(deps, env, address, commission_rate)
+        }
+        ExecuteMsg::Activate { address } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_active(deps, env, address, true)
+        }
+        ExecuteMsg::Deactivate { address } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_active(deps, env, address, false)
+        }
+        ExecuteMsg::TransferAdmin { new_admin } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_transfer_admin(deps, new_admin)
+        }
+        ExecuteMsg::UpdateScoringParams { params } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_update_scoring_params(deps, params)
+        }
+        ExecuteMsg::RecomputeWeights {} => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_recompute_weights(deps, env)
+        }
+        ExecuteMsg::SetWeights { weights, normalize } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_weights(deps, env, weights, normalize)
+        }
+        ExecuteMsg::UpdateWeightLimits { limits } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_update_weight_limits(deps, limits)
+        }
+        ExecuteMsg::AdvanceWeights {} => exec_advance_weights(deps, env),
+        ExecuteMsg::SetOracle { oracle } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_oracle(deps, oracle)
+        }
+        ExecuteMsg::SetConsumer { address, name, validators } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_consumer(deps, address, name, validators)
+        }
+        ExecuteMsg::RemoveConsumer { address } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_remove_consumer(deps, env, address)
+        }
+        ExecuteMsg::SetConsumerWeight { consumer, validator, weight } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_consumer_weight(deps, consumer, validator, weight)
+        }
+        ExecuteMsg::UpdateOracleParams { params } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            ORACLE_PARAMS.save(deps.storage, &params)?;
+            Ok(Response::new().add_attribute("action", "update_oracle_params"))
+        }
+        ExecuteMsg::SyncValidators {} => exec_sync_validators(deps, env),
+        ExecuteMsg::ValidatorDrained { address } => exec_validator_drained(deps, info, address),
+        ExecuteMsg::DelegationReport { delegations } => {
+            exec_delegation_report(deps, env, info, delegations)
+        }
+        ExecuteMsg::SetGovernance { governance } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_governance(deps, governance)
+        }
+        ExecuteMsg::UpdateApplicationRules { rules } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_update_application_rules(deps, rules)
+        }
+        ExecuteMsg::SubmitApplication { application } => {
+            exec_submit_application(deps, env, info, application)
+        }
+        ExecuteMsg::WithdrawApplication { address } => {
+            exec_withdraw_application(deps, info, address)
+        }
+        ExecuteMsg::ApproveApplication { address, weight } => {
+            exec_approve_application(deps, env, info, address, weight)
+        }
+        ExecuteMsg::RejectApplication { address, reason } => {
+            exec_reject_application(deps, info, address, reason)
+        }
+        ExecuteMsg::UpdatePolicy { policy } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_update_policy(deps, policy)
+        }
+        ExecuteMsg::EvaluatePolicy {} => exec_evaluate_policy(deps, env),
+        ExecuteMsg::UpdateHistoryParams { params } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            HISTORY_PARAMS.save(deps.storage, &params)?;
+            Ok(Response::new().add_attribute("action", "update_history_params"))
+        }
+        ExecuteMsg::PruneHistory { address, limit } => {
+            exec_prune_history(deps, env, address, limit)
+        }
+        ExecuteMsg::SetAttester { attester } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_attester(deps, attester)
+        }
+        ExecuteMsg::SetProfileManager { address, manager } => {
+            ensure_admin(deps.as_ref(), &info)?;
+            exec_set_profile_manager(deps, env, address, manager)
+        }
+        ExecuteMsg::UpdateProfile { address, profile } => {
+            exec_update_profile(deps, env, info, address, profile)
+        }
+        ExecuteMsg::SetProfileVerified { address, verified } => {
+            exec_set_profile_verified(deps, env, info, address, verified)
+        }
+        ExecuteMsg::SubmitMetrics { epoch, metrics, bonded_tokens } => {
+            exec_submit_metrics(deps, env, info, epoch, metrics, bonded_tokens)
         }
-        ExecuteMsg::Activate { address } => exec_set_active(deps, address, true),
-        ExecuteMsg::Deactivate { address } => exec_set_active(deps, address, false),
-        ExecuteMsg::TransferAdmin { new_admin } => exec_transfer_admin(deps, new_admin),
     }
 }
 
-fn exec_add_validator(deps: DepsMut, params: ValidatorParams) -> Result<Response, StdError> {
-    let _ = deps.api.addr_validate(&params.address)?;
+fn exec_add_validator(
+    deps: DepsMut,
+    env: Env,
+    params: ValidatorParams,
+) -> Result<Response, ContractError> {
+    validate_validator(deps.as_ref(), &params.address)?;
     if VALIDATORS.may_load(deps.storage, params.address.clone())?.is_some() {
-        return Err(StdError::generic_err("validator already exists"));
+        return Err(ContractError::Std(StdError::generic_err("validator already exists")));
     }
-    VALIDATORS.save(
-        deps.storage,
+    let v = new_validator_info(
         params.address.clone(),
-        &ValidatorInfo {
-            address: params.address.clone(),
-            delegated_amount: Uint128::zero(),
-            weight: params.weight,
-            last_reward_claim: cosmwasm_std::Timestamp::from_seconds(0),
-            slashing_events: 0,
-            uptime_percentage: Decimal::percent(100),
-            commission_rate: params.commission_rate,
-            is_active: true,
-        },
-    )?;
+        params.weight,
+        params.commission_rate,
+        cosmwasm_std::Timestamp::from_seconds(0),
+    );
+    VALIDATORS.save(deps.storage, params.address.clone(), &v)?;
+    record_snapshot(deps.storage, &v, env.block.time)?;
     Ok(Response::new()
+        .add_submessages(consumer_update_msgs(deps)?)
         .add_attribute("action", "add_validator")
         .add_attribute("validator", params.address))
 }
 
-fn exec_remove_validator(deps: DepsMut, address: String) -> Result<Response, StdError> {
-    if VALIDATORS.may_load(deps.storage, address.clone())?.is_none() {
-        return Err(StdError::not_found("ValidatorInfo"));
-    }
-    VALIDATORS.remove(deps.storage, address.clone());
+fn exec_remove_validator(
+    deps: DepsMut,
+    env: Env,
+    address: String,
+) -> Result<Response, ContractError> {
+    let Some(mut v) = VALIDATORS.may_load(deps.storage, address.clone())? else {
+        return Err(ContractError::Std(StdError::not_found("ValidatorInfo")));
+    };
+    // Consumers delegating to the validator may still hold stake with it; keep it until
+    // each of them reports it drained
+    let serving = consumers_serving(deps.as_ref(), &address)?;
+    v.is_active = false;
+    let status = if serving.is_empty() {
+        delete_validator(deps.storage, &address)?;
+        "removed"
+    } else {
+        v.draining = true;
+        VALIDATORS.save(deps.storage, address.clone(), &v)?;
+        for consumer in &serving {
+            PENDING_DRAINS.save(deps.storage, (&address, consumer), &true)?;
+        }
+        "draining"
+    };
+    // History outlives the validator so the removal stays auditable
+    record_snapshot(deps.storage, &v, env.block.time)?;
     Ok(Response::new()
+        .add_submessages(consumer_update_msgs(deps)?)
         .add_attribute("action", "remove_validator")
-        .add_attribute("validator", address))
+        .add_attribute("validator", address)
+        .add_attribute("status", status))
 }
 
-fn exec_update_weight(deps: DepsMut, address: String, weight: Decimal) -> Result<Response, StdError> {
-    VALIDATORS.update(deps.storage, address.clone(), |maybe| match maybe {
-        Some(mut v) => {
-            v.weight = weight;
-            Ok(v)
+fn exec_validator_drained(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    if !CONSUMERS.has(deps.storage, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Consumers also report validators that merely left their view; only pending removals count
+    PENDING_DRAINS.remove(deps.storage, (&address, &info.sender));
+    let removed = match VALIDATORS.may_load(deps.storage, address.clone())? {
+        Some(v) if v.draining && !has_pending_drains(deps.storage, &address) => {
+            delete_validator(deps.storage, &address)?;
+            true
         }
-        None => Err(StdError::not_found("ValidatorInfo")),
-    })?;
+        _ => false,
+    };
     Ok(Response::new()
+        .add_attribute("action", "validator_drained")
+        .add_attribute("validator", address)
+        .add_attribute("removed", removed.to_string()))
+}
+
+fn exec_update_weight(
+    deps: DepsMut,
+    env: Env,
+    address: String,
+    weight: Decimal,
+) -> Result<Response, ContractError> {
+    let mut v = VALIDATORS.load(deps.storage, address.clone())?;
+    let changed = move_weight(deps.storage, &mut v, weight, env.block.time)?;
+    let mut res = Response::new();
+    if changed {
+        res = res.add_submessages(consumer_update_msgs(deps)?);
+    }
+    Ok(res
         .add_attribute("action", "update_weight")
         .add_attribute("validator", address)
-        .add_attribute("weight", weight.to_string()))
+        .add_attribute("weight", v.weight.to_string())
+        .add_attribute("target", weight.to_string()))
 }
 
 fn exec_set_commission(
     deps: DepsMut,
+    env: Env,
     address: String,
     commission_rate: Decimal,
-) -> Result<Response, StdError> {
-    VALIDATORS.update(deps.storage, address.clone(), |maybe| match maybe {
+) -> Result<Response, ContractError> {
+    let v = VALIDATORS.update(deps.storage, address.clone(), |maybe| match maybe {
         Some(mut v) => {
             v.commission_rate = commission_rate;
             Ok(v)
         }
         None => Err(StdError::not_found("ValidatorInfo")),
     })?;
+    record_snapshot(deps.storage, &v, env.block.time)?;
     Ok(Response::new()
         .add_attribute("action", "set_commission")
         .add_attribute("validator", address)
         .add_attribute("commission", commission_rate.to_string()))
 }
 
-fn exec_set_active(deps: DepsMut, address: String, is_active: bool) -> Result<Response, StdError> {
-    VALIDATORS.update(deps.storage, address.clone(), |maybe| match maybe {
+/// Refresh commission from the staking module and deactivate validators that left the
+/// bonded set, emitting an event per change. Permissionless: it only mirrors chain state.
+fn exec_sync_validators(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let validators = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| r.map(|(_, v)| v))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let bonded = bonded_validators(deps.as_ref())?;
+    let mut events = Vec::new();
+    let mut deactivated = false;
+    for mut v in validators {
+        match bonded.get(&v.address) {
+            Some(chain) if chain.commission != v.commission_rate => {
+                events.push(
+                    Event::new("validator_commission_changed")
+                        .add_attribute("validator", &v.address)
+                        .add_attribute("old", v.commission_rate.to_string())
+                        .add_attribute("new", chain.commission.to_string()),
+                );
+                v.commission_rate = chain.commission;
+            }
+            // The bonded set leaves out jailed, unbonding and unbonded validators
+            None if v.is_active => {
+                events.push(
+                    Event::new("validator_deactivated")
+                        .add_attribute("validator", &v.address)
+                        .add_attribute("reason", "not_bonded"),
+                );
+                v.is_active = false;
+                deactivated = true;
+            }
+            _ => continue,
+        }
+        VALIDATORS.save(deps.storage, v.address.clone(), &v)?;
+        record_snapshot(deps.storage, &v, env.block.time)?;
+    }
+
+    let mut res = Response::new();
+    if deactivated {
+        res = res.add_submessages(consumer_update_msgs(deps)?);
+    }
+    Ok(res
+        .add_attribute("action", "sync_validators")
+        .add_attribute("changes", events.len().to_string())
+        .add_events(events))
+}
+
+fn exec_set_active(
+    deps: DepsMut,
+    env: Env,
+    address: String,
+    is_active: bool,
+) -> Result<Response, ContractError> {
+    let v = VALIDATORS.update(deps.storage, address.clone(), |maybe| match maybe {
         Some(mut v) => {
             v.is_active = is_active;
+            // Re-activating a validator cancels a pending removal
+            if is_active {
+                v.draining = false;
+            }
             Ok(v)
         }
         None => Err(StdError::not_found("ValidatorInfo")),
     })?;
+    record_snapshot(deps.storage, &v, env.block.time)?;
+    // An admin override restarts the policy grace period
+    if is_active {
+        POLICY_VIOLATIONS.remove(deps.storage, address.clone());
+        clear_pending_drains(deps.storage, &address)?;
+    }
     Ok(Response::new()
+        .add_submessages(consumer_update_msgs(deps)?)
         .add_attribute("action", if is_active { "activate" } else { "deactivate" })
         .add_attribute("validator", address))
 }
 
-fn exec_transfer_admin(deps: DepsMut, new_admin: String) -> Result<Response, StdError> {
+fn exec_transfer_admin(deps: DepsMut, new_admin: String) -> Result<Response, ContractError> {
     let mut cfg = CONFIG.load(deps.storage)?;
     cfg.admin = deps.api.addr_validate(&new_admin)?;
     CONFIG.save(deps.storage, &cfg)?;
@@ -157,39 +440,729 @@ fn exec_transfer_admin(deps: DepsMut, new_admin: String) -> Result<Response, Std
         .add_attribute("new_admin", new_admin))
 }
 
+fn exec_set_oracle(deps: DepsMut, oracle: Option<String>) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    cfg.oracle = oracle.as_deref().map(|o| deps.api.addr_validate(o)).transpose()?;
+    CONFIG.save(deps.storage, &cfg)?;
+    Ok(Response::new()
+        .add_attribute("action", "set_oracle")
+        .add_attribute("oracle", oracle.unwrap_or_else(|| "none".to_string())))
+}
+
+fn exec_set_governance(
+    deps: DepsMut,
+    governance: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    cfg.governance = governance.as_deref().map(|g| deps.api.addr_validate(g)).transpose()?;
+    CONFIG.save(deps.storage, &cfg)?;
+    Ok(Response::new()
+        .add_attribute("action", "set_governance")
+        .add_attribute("governance", governance.unwrap_or_else(|| "none".to_string())))
+}
+
+fn exec_set_attester(deps: DepsMut, attester: Option<String>) -> Result<Response, ContractError> {
+    let mut cfg = CONFIG.load(deps.storage)?;
+    cfg.attester = attester.as_deref().map(|a| deps.api.addr_validate(a)).transpose()?;
+    CONFIG.save(deps.storage, &cfg)?;
+    Ok(Response::new()
+        .add_attribute("action", "set_attester")
+        .add_attribute("attester", attester.unwrap_or_else(|| "none".to_string())))
+}
+
+fn exec_update_scoring_params(
+    deps: DepsMut,
+    params: ScoringParams,
+) -> Result<Response, ContractError> {
+    if params.uptime_coefficient.is_zero() && params.commission_coefficient.is_zero() {
+        return Err(ContractError::Std(StdError::generic_err(
+            "scoring coefficients cannot all be zero",
+        )));
+    }
+    SCORING.save(deps.storage, &params)?;
+    Ok(Response::new().add_attribute("action", "update_scoring_params"))
+}
+
+fn exec_recompute_weights(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let scores = compute_scores(deps.as_ref(), &env)?;
+    if scores.iter().all(|s| s.weight.is_zero()) {
+        return Err(ContractError::Std(StdError::generic_err("no eligible validators to weight")));
+    }
+    let mut updated = 0u32;
+    for s in scores {
+        let mut v = VALIDATORS.load(deps.storage, s.address.clone())?;
+        if !v.is_active {
+            continue;
+        }
+        if move_weight(deps.storage, &mut v, s.weight, env.block.time)? {
+            updated += 1;
+        }
+    }
+    Ok(Response::new()
+        .add_submessages(consumer_update_msgs(deps)?)
+        .add_attribute("action", "recompute_weights")
+        .add_attribute("updated", updated.to_string()))
+}
+
+fn compute_scores(deps: Deps, env: &Env) -> StdResult<Vec<ValidatorScore>> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let params = SCORING.load(deps.storage)?;
+    let oracle_params = ORACLE_PARAMS.load(deps.storage)?;
+    let mut scores = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| {
+            let (addr, v) = r?;
+            let metrics = VALIDATOR_METRICS.may_load(deps.storage, addr.clone())?;
+            let stale = is_stale(&cfg, &oracle_params, metrics.as_ref(), env.block.time);
+            let violating = POLICY_VIOLATIONS.has(deps.storage, addr);
+            Ok(score_validator(&params, &v, stale || violating))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    assign_weights(&params, &mut scores);
+    Ok(scores)
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        id if id >= CONSUMER_UPDATE_REPLY_BASE => handle_consumer_update_reply(
+            deps,
+            env,
+            id - CONSUMER_UPDATE_REPLY_BASE,
+            msg.result,
+        ),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+/// Re-save every validator so the `VALIDATORS` indexes cover entries written before an index
+/// existed, and store default parameters for features added since the stored version
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    save_default(deps.storage, &SCORING)?;
+    save_default(deps.storage, &ORACLE_PARAMS)?;
+    save_default(deps.storage, &APPLICATION_RULES)?;
+    save_default(deps.storage, &POLICY)?;
+    save_default(deps.storage, &HISTORY_PARAMS)?;
+    save_default(deps.storage, &WEIGHT_LIMITS)?;
+    let validators: Vec<(String, ValidatorInfo)> = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for (address, v) in &validators {
+        VALIDATORS.save(deps.storage, address.clone(), v)?;
+    }
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("contract_version", CONTRACT_VERSION)
+        .add_attribute("reindexed", validators.len().to_string()))
+}
+
+/// Store `T::default()` in `item` unless it already holds a value
+fn save_default<T>(storage: &mut dyn Storage, item: &Item<T>) -> StdResult<()>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    if !item.exists(storage) {
+        item.save(storage, &T::default())?;
+    }
+    Ok(())
+}
+
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_binary(&query_config(deps)?),
-        QueryMsg::Validators {} => to_binary(&query_validators(deps)?),
+        QueryMsg::Validators { status, min_weight, attestation, order_by, start_after, limit } => {
+            to_binary(&query_validators(
+                deps,
+                status,
+                min_weight,
+                attestation,
+                order_by,
+                start_after,
+                limit,
+            )?)
+        }
         QueryMsg::Validator { address } => to_binary(&query_validator(deps, address)?),
+        QueryMsg::ActiveValidators {} => to_binary(&query_active_validators(deps)?),
+        QueryMsg::Consumers {} => to_binary(&query_consumers(deps)?),
+        QueryMsg::Consumer { address } => to_binary(&query_consumer(deps, address)?),
+        QueryMsg::ConsumerValidators { consumer } => {
+            to_binary(&query_consumer_validators(deps, consumer)?)
+        }
+        QueryMsg::VotingPower {} => to_binary(&query_voting_power(deps, env)?),
+        QueryMsg::NetworkStats {} => to_binary(&NETWORK_STATS.may_load(deps.storage)?),
+        QueryMsg::StakeSummary {} => to_binary(&query_stake_summary(deps)?),
+        QueryMsg::ValidatorStake { address } => to_binary(&query_validator_stake(deps, address)?),
+        QueryMsg::ScoringParams {} => to_binary(&SCORING.load(deps.storage)?),
+        QueryMsg::ScoreBreakdown {} => to_binary(&ScoreBreakdownResponse {
+            scores: compute_scores(deps, &env)?,
+        }),
+        QueryMsg::OracleParams {} => to_binary(&ORACLE_PARAMS.load(deps.storage)?),
+        QueryMsg::ValidatorMetrics { address } => {
+            to_binary(&query_validator_metrics(deps, env, address)?)
+        }
+        QueryMsg::MetricsSubmissions { start_after, limit } => {
+            to_binary(&query_metrics_submissions(deps, start_after, limit)?)
+        }
+        QueryMsg::WeightSchedule { start_after, limit } => {
+            to_binary(&query_weight_schedule(deps, env, start_after, limit)?)
+        }
+        QueryMsg::HistoryParams {} => to_binary(&HISTORY_PARAMS.load(deps.storage)?),
+        QueryMsg::ValidatorHistory { address, start, end, limit } => {
+            to_binary(&query_validator_history(deps, address, start, end, limit)?)
+        }
+        QueryMsg::Profile { address } => to_binary(&query_profile(deps, address)?),
+        QueryMsg::Profiles { attestation, verified, start_after, limit } => {
+            to_binary(&query_profiles(deps, attestation, verified, start_after, limit)?)
+        }
+        QueryMsg::Policy {} => to_binary(&POLICY.load(deps.storage)?),
+        QueryMsg::PolicyReport {} => to_binary(&query_policy_report(deps)?),
+        QueryMsg::ApplicationRules {} => to_binary(&APPLICATION_RULES.load(deps.storage)?),
+        QueryMsg::Application { address } => to_binary(&query_application(deps, address)?),
+        QueryMsg::Applications { start_after, limit } => {
+            to_binary(&query_applications(deps, start_after, limit)?)
+        }
     }
 }
 
 fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let cfg = CONFIG.load(deps.storage)?;
     let total_active = VALIDATORS
-        .range(deps.storage, None, None, Order::Ascending)
-        .filter_map(|r| r.ok())
-        .map(|(_, v)| v)
-        .filter(|v| v.is_active)
+        .idx
+        .status
+        .prefix(ValidatorStatus::Active.as_str().to_string())
+        .keys_raw(deps.storage, None, None, Order::Ascending)
         .count() as u32;
 
     Ok(ConfigResponse {
         admin: cfg.admin.to_string(),
+        oracle: cfg.oracle.map(|o| o.to_string()),
+        governance: cfg.governance.map(|g| g.to_string()),
+        attester: cfg.attester.map(|a| a.to_string()),
         total_active,
     })
 }
 
-fn query_validators(deps: Deps) -> StdResult<ValidatorsResponse> {
-    let vals = VALIDATORS
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|r| r.map(|(_, v)| v))
-        .collect::<Result<Vec<_>, _>>()?;
-    Ok(ValidatorsResponse { validators: vals })
+type ValidatorRecords<'a> = Box<dyn Iterator<Item = StdResult<(String, ValidatorInfo)>> + 'a>;
+
+fn query_validators(
+    deps: Deps,
+    status: Option<ValidatorStatus>,
+    min_weight: Option<Decimal>,
+    attestation: Option<String>,
+    order_by: Option<ValidatorOrder>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ValidatorsResponse> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    // Secondary orders resume after the (key, address) of the last validator returned
+    let cursor = |key: fn(&ValidatorInfo) -> u128| -> StdResult<Option<Bound<(u128, String)>>> {
+        start_after
+            .clone()
+            .map(|address| {
+                let v = VALIDATORS.load(deps.storage, address.clone())?;
+                Ok(Bound::exclusive((key(&v), address)))
+            })
+            .transpose()
+    };
+
+    let records: ValidatorRecords = match order_by.unwrap_or(ValidatorOrder::Address) {
+        ValidatorOrder::Address => {
+            let start = start_after.clone().map(Bound::exclusive);
+            match status {
+                Some(s) => VALIDATORS.idx.status.prefix(s.as_str().to_string()).range(
+                    deps.storage,
+                    start,
+                    None,
+                    Order::Ascending,
+                ),
+                None => VALIDATORS.range(deps.storage, start, None, Order::Ascending),
+            }
+        }
+        ValidatorOrder::Weight => {
+            // The empty address sorts first, so the bound keeps every validator at `min_weight`
+            let min = min_weight.map(|w| Bound::inclusive((w.atomics().u128(), String::new())));
+            let max = cursor(|v| v.weight.atomics().u128())?;
+            VALIDATORS.idx.weight.range(deps.storage, min, max, Order::Descending)
+        }
+        ValidatorOrder::DelegatedAmount => {
+            let max = cursor(|v| v.delegated_amount.u128())?;
+            VALIDATORS.idx.delegated.range(deps.storage, None, max, Order::Descending)
+        }
+    };
+
+    let matches = |address: &String, v: &ValidatorInfo| -> StdResult<bool> {
+        if !(status.map_or(true, |s| ValidatorStatus::of(v) == s)
+            && min_weight.map_or(true, |w| v.weight >= w))
+        {
+            return Ok(false);
+        }
+        match &attestation {
+            Some(kind) => Ok(PROFILES
+                .may_load(deps.storage, address.clone())?
+                .map_or(false, |p| p.attestations.iter().any(|a| &a.kind == kind))),
+            None => Ok(true),
+        }
+    };
+    let validators = records
+        .filter_map(|r| {
+            r.and_then(|(address, v)| Ok(matches(&address, &v)?.then_some(v)))
+                .transpose()
+        })
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ValidatorsResponse { validators })
 }
 
 fn query_validator(deps: Deps, address: String) -> StdResult<ValidatorResponse> {
     let val = VALIDATORS.may_load(deps.storage, address.clone())?;
     Ok(ValidatorResponse { validator: val })
-}
\ No newline at end of file
+}
+fn query_active_validators(deps: Deps) -> StdResult<ActiveValidatorsResponse> {
+    Ok(ActiveValidatorsResponse {
+        validators: active_validator_params(deps)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::marker::PhantomData;
+
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        coin, coins, from_json, to_json_binary, BankMsg, Coin, ContractResult, CosmosMsg, Empty,
+        OwnedDeps, Querier, QuerierResult, QueryRequest, ReplyOn, StakingQuery, Storage,
+        SubMsgResult, SystemResult, Validator, ValidatorResponse as ChainValidatorResponse,
+    };
+
+    use cosmwasm_std::Uint128;
+    use regen_types::DelegationAmount;
+
+    use crate::msg::{
+        ApplicationParams, ConsumerParams, ConsumerResponse, ValidatorMetrics,
+        ValidatorStakeResponse,
+    };
+    use crate::state::{ApplicationRules, APPLICATIONS};
+
+    const ADMIN: &str = "admin";
+    const VAL1: &str = "regenvaloper1a";
+    const VAL2: &str = "regenvaloper1b";
+    const HUB1: &str = "hub1";
+    const HUB2: &str = "hub2";
+
+    fn chain_validator(address: &str) -> Validator {
+        Validator {
+            address: address.to_string(),
+            commission: Decimal::percent(5),
+            max_commission: Decimal::percent(20),
+            max_change_rate: Decimal::percent(1),
+        }
+    }
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        deps.querier
+            .update_staking("uregen", &[chain_validator(VAL1), chain_validator(VAL2)], &[]);
+        let validators = [VAL1, VAL2]
+            .iter()
+            .map(|address| ValidatorParams {
+                address: address.to_string(),
+                weight: Decimal::percent(50),
+                commission_rate: Decimal::percent(5),
+            })
+            .collect();
+        let msg = InstantiateMsg {
+            admin: ADMIN.to_string(),
+            validators,
+            scoring: None,
+            oracle: None,
+            oracle_params: None,
+            consumers: [HUB1, HUB2]
+                .iter()
+                .map(|hub| ConsumerParams {
+                    address: hub.to_string(),
+                    name: hub.to_string(),
+                    validators: None,
+                })
+                .collect(),
+            governance: None,
+            attester: None,
+            application_rules: None,
+            policy: None,
+            history_params: None,
+            weight_limits: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        deps
+    }
+
+    /// Staking module that still answers `Validator` queries for `jailed` after it has left the
+    /// bonded set, as the chain does for jailed and unbonded validators
+    struct JailedQuerier {
+        base: MockQuerier,
+        jailed: Validator,
+    }
+
+    impl Querier for JailedQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_json(bin_request) {
+                Ok(QueryRequest::<Empty>::Staking(StakingQuery::Validator { address }))
+                    if address == self.jailed.address =>
+                {
+                    let res = ChainValidatorResponse {
+                        validator: Some(self.jailed.clone()),
+                    };
+                    SystemResult::Ok(ContractResult::Ok(to_json_binary(&res).unwrap()))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    /// `deps` with only `bonded` in the bonded set and `jailed` still known to the chain
+    fn jail(
+        deps: OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        jailed: &str,
+        bonded: &[&str],
+    ) -> OwnedDeps<MockStorage, MockApi, JailedQuerier> {
+        let mut base = deps.querier;
+        let bonded: Vec<Validator> = bonded.iter().map(|v| chain_validator(v)).collect();
+        base.update_staking("uregen", &bonded, &[]);
+        let deps = OwnedDeps {
+            storage: deps.storage,
+            api: deps.api,
+            querier: JailedQuerier {
+                base,
+                jailed: chain_validator(jailed),
+            },
+            custom_query_type: PhantomData,
+        };
+        assert!(deps.as_ref().querier.query_validator(jailed).unwrap().is_some());
+        deps
+    }
+
+    #[test]
+    fn validators_outside_the_bonded_set_are_rejected() {
+        let mut deps = jail(setup(), "regenvaloper1c", &[VAL1, VAL2]);
+        let add = ExecuteMsg::AddValidator {
+            params: ValidatorParams {
+                address: "regenvaloper1c".to_string(),
+                weight: Decimal::percent(10),
+                commission_rate: Decimal::percent(5),
+            },
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), add).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidValidator { .. }));
+    }
+
+    #[test]
+    fn sustained_uptime_changes_are_accepted_after_confirmation() {
+        let mut deps = setup();
+        let set_oracle = ExecuteMsg::SetOracle {
+            oracle: Some("oracle".to_string()),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), set_oracle).unwrap();
+        let submit = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, epoch, pct| {
+            let msg = ExecuteMsg::SubmitMetrics {
+                epoch,
+                metrics: vec![ValidatorMetrics {
+                    address: VAL1.to_string(),
+                    uptime_percentage: Decimal::percent(pct),
+                    missed_blocks: 0,
+                    slashing_events: 0,
+                    voting_power: None,
+                    self_bond: None,
+                    voting_power_rank: None,
+                }],
+                bonded_tokens: None,
+            };
+            let res = execute(deps.as_mut(), mock_env(), mock_info("oracle", &[]), msg).unwrap();
+            res.attributes[2].value == "1"
+        };
+        let uptime = |deps: Deps| {
+            VALIDATOR_METRICS.load(deps.storage, VAL1.to_string()).unwrap().uptime_percentage
+        };
+
+        assert!(submit(&mut deps, 1, 99));
+        // A one-off dip is rejected and forgotten once reports return to the accepted level
+        assert!(!submit(&mut deps, 2, 50));
+        assert!(submit(&mut deps, 3, 99));
+
+        // Reports that disagree with each other do not confirm a move
+        assert!(!submit(&mut deps, 4, 50));
+        assert!(!submit(&mut deps, 5, 10));
+        assert!(!submit(&mut deps, 6, 55));
+        assert_eq!(uptime(deps.as_ref()), Decimal::percent(99));
+
+        // The third consecutive agreeing report accepts the new level
+        assert!(!submit(&mut deps, 7, 52));
+        assert!(submit(&mut deps, 8, 51));
+        assert_eq!(uptime(deps.as_ref()), Decimal::percent(51));
+        assert!(submit(&mut deps, 9, 60));
+    }
+
+    #[test]
+    fn sync_deactivates_jailed_validators() {
+        let mut deps = jail(setup(), VAL2, &[VAL1]);
+        let sync = ExecuteMsg::SyncValidators {};
+        let res = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), sync).unwrap();
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(res.events[0].ty, "validator_deactivated");
+        assert!(VALIDATORS.load(deps.as_ref().storage, VAL1.to_string()).unwrap().is_active);
+        assert!(!VALIDATORS.load(deps.as_ref().storage, VAL2.to_string()).unwrap().is_active);
+    }
+
+    #[test]
+    fn recompute_weights_is_admin_only() {
+        let mut deps = setup();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::RecomputeWeights {},
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), ExecuteMsg::RecomputeWeights {})
+            .unwrap();
+    }
+
+    #[test]
+    fn failed_consumer_push_is_recorded_without_failing() {
+        let mut deps = setup();
+        let msg = ExecuteMsg::UpdateWeight {
+            address: VAL1.to_string(),
+            weight: Decimal::percent(30),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        assert!(res.messages.iter().all(|m| m.reply_on == ReplyOn::Error));
+
+        // The second consumer in address order rejects its push
+        let failed = Reply {
+            id: res.messages[1].id,
+            result: SubMsgResult::Err("redelegation limit".to_string()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), failed).unwrap();
+        assert!(res.messages.is_empty());
+
+        let consumer = |deps: Deps, hub: &str| -> ConsumerResponse {
+            let msg = QueryMsg::Consumer {
+                address: hub.to_string(),
+            };
+            from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+        assert_eq!(consumer(deps.as_ref(), HUB1).last_push_failure, None);
+        let failure = consumer(deps.as_ref(), HUB2).last_push_failure.unwrap();
+        assert_eq!(failure.error, "redelegation limit");
+
+        // A consumer registered ahead of the others in address order is pushed alone, and its
+        // failure is recorded against it rather than whichever consumer now sorts at that position
+        let add = ExecuteMsg::SetConsumer {
+            address: "hub0".to_string(),
+            name: "Hub 0".to_string(),
+            validators: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), add).unwrap();
+        let failed = Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Err("not a hub".to_string()),
+        };
+        reply(deps.as_mut(), mock_env(), failed).unwrap();
+        let failure = consumer(deps.as_ref(), "hub0").last_push_failure.unwrap();
+        assert_eq!(failure.error, "not a hub");
+        assert_eq!(consumer(deps.as_ref(), HUB1).last_push_failure, None);
+
+        let unknown = Reply {
+            id: 7,
+            result: SubMsgResult::Err("".to_string()),
+        };
+        let err = reply(deps.as_mut(), mock_env(), unknown).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownReplyId { id: 7 }));
+    }
+
+    #[test]
+    fn delegation_reports_update_only_listed_validators() {
+        let mut deps = setup();
+        let report = |delegations: &[(&str, u128)]| ExecuteMsg::DelegationReport {
+            delegations: delegations
+                .iter()
+                .map(|(validator, amount)| DelegationAmount {
+                    validator: validator.to_string(),
+                    amount: Uint128::new(*amount),
+                })
+                .collect(),
+        };
+        let delegated = |deps: Deps, validator: &str| -> Uint128 {
+            let msg = QueryMsg::ValidatorStake {
+                address: validator.to_string(),
+            };
+            let res: ValidatorStakeResponse =
+                from_json(query(deps, mock_env(), msg).unwrap()).unwrap();
+            res.delegated_amount
+        };
+
+        // Only registered consumers are trusted to report stake
+        let msg = report(&[(VAL1, 100)]);
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let msg = report(&[(VAL1, 100), (VAL2, 200)]);
+        execute(deps.as_mut(), mock_env(), mock_info(HUB1, &[]), msg).unwrap();
+        let msg = report(&[(VAL1, 50)]);
+        execute(deps.as_mut(), mock_env(), mock_info(HUB2, &[]), msg).unwrap();
+
+        // A later report leaves unlisted validators alone, and zero clears one
+        let msg = report(&[(VAL1, 0)]);
+        execute(deps.as_mut(), mock_env(), mock_info(HUB1, &[]), msg).unwrap();
+        assert_eq!(delegated(deps.as_ref(), VAL1), Uint128::new(50));
+        assert_eq!(delegated(deps.as_ref(), VAL2), Uint128::new(200));
+
+        let msg = report(&[(VAL2, 1), (VAL2, 2)]);
+        execute(deps.as_mut(), mock_env(), mock_info(HUB1, &[]), msg).unwrap_err();
+    }
+
+    #[test]
+    fn application_deposits_are_refunded() {
+        let mut deps = setup();
+        let (val3, val4) = ("regenvaloper1c", "regenvaloper1d");
+        let chain: Vec<Validator> =
+            [VAL1, VAL2, val3, val4].iter().map(|v| chain_validator(v)).collect();
+        deps.querier.update_staking("uregen", &chain, &[]);
+        let rules = ExecuteMsg::UpdateApplicationRules {
+            rules: ApplicationRules {
+                deposit: Some(coin(1_000, "uregen")),
+                ..ApplicationRules::default()
+            },
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), rules).unwrap();
+
+        let submit = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+                      address: &str,
+                      funds: &[Coin]| {
+            let application = ApplicationParams {
+                address: address.to_string(),
+                moniker: address.to_string(),
+                website: None,
+                details: None,
+                commission_commitment: Decimal::percent(5),
+            };
+            let msg = ExecuteMsg::SubmitApplication { application };
+            execute(deps.as_mut(), mock_env(), mock_info("applicant", funds), msg)
+        };
+        let refund = BankMsg::Send {
+            to_address: "applicant".to_string(),
+            amount: coins(1_000, "uregen"),
+        };
+        let refunded = |res: &Response| {
+            res.messages.iter().any(|m| m.msg == CosmosMsg::Bank(refund.clone()))
+        };
+
+        submit(&mut deps, val3, &[]).unwrap_err();
+        submit(&mut deps, val3, &coins(1_000, "uregen")).unwrap();
+        let withdraw = ExecuteMsg::WithdrawApplication {
+            address: val3.to_string(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), withdraw.clone())
+            .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res =
+            execute(deps.as_mut(), mock_env(), mock_info("applicant", &[]), withdraw).unwrap();
+        assert!(refunded(&res));
+
+        submit(&mut deps, val3, &coins(1_000, "uregen")).unwrap();
+        let reject = ExecuteMsg::RejectApplication {
+            address: val3.to_string(),
+            reason: "incomplete profile".to_string(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("applicant", &[]), reject.clone())
+            .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), reject).unwrap();
+        assert!(refunded(&res));
+
+        submit(&mut deps, val4, &coins(1_000, "uregen")).unwrap();
+        let approve = ExecuteMsg::ApproveApplication {
+            address: val4.to_string(),
+            weight: Decimal::percent(10),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), approve).unwrap();
+        assert!(refunded(&res));
+        assert!(VALIDATORS.has(deps.as_ref().storage, val4.to_string()));
+        assert!(APPLICATIONS.is_empty(deps.as_ref().storage));
+    }
+
+    #[test]
+    fn migrate_upgrades_storage_from_the_first_release() {
+        // The first release stored only the admin and validators, in a plain map without
+        // indexes or the draining flag
+        let mut deps = mock_dependencies();
+        deps.storage.set(b"config", br#"{"admin":"admin"}"#);
+        for address in [VAL1, VAL2] {
+            let old = format!(
+                r#"{{"address":"{address}","delegated_amount":"0","weight":"0.5",
+                "last_reward_claim":"0","slashing_events":0,"uptime_percentage":"1",
+                "commission_rate":"0.05","is_active":true}}"#
+            );
+            deps.storage.set(&VALIDATORS.key(address.to_string()), old.as_bytes());
+        }
+        let by_weight = |deps: Deps| {
+            query_validators(deps, None, None, None, Some(ValidatorOrder::Weight), None, None)
+                .unwrap()
+                .validators
+        };
+        assert!(by_weight(deps.as_ref()).is_empty());
+
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[2].value, "2");
+        let validators = by_weight(deps.as_ref());
+        assert_eq!(validators.len(), 2);
+        assert!(validators.iter().all(|v| !v.draining));
+
+        // Every feature reads its parameters again
+        for msg in [
+            QueryMsg::ScoringParams {},
+            QueryMsg::OracleParams {},
+            QueryMsg::ApplicationRules {},
+            QueryMsg::Policy {},
+            QueryMsg::HistoryParams {},
+        ] {
+            query(deps.as_ref(), mock_env(), msg).unwrap();
+        }
+        assert_eq!(WEIGHT_LIMITS.load(deps.as_ref().storage).unwrap(), Default::default());
+        let recompute = ExecuteMsg::RecomputeWeights {};
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), recompute).unwrap();
+
+        // Parameters already set are kept
+        let scoring = ScoringParams {
+            min_weight: Decimal::percent(5),
+            ..ScoringParams::default()
+        };
+        SCORING.save(deps.as_mut().storage, &scoring).unwrap();
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(SCORING.load(deps.as_ref().storage).unwrap(), scoring);
+    }
+
+    #[test]
+    fn attestation_filter_surfaces_profile_errors() {
+        let mut deps = setup();
+        deps.storage.set(&PROFILES.key(VAL1.to_string()), b"not a profile");
+        let err = query_validators(
+            deps.as_ref(),
+            None,
+            None,
+            Some("audit".to_string()),
+            None,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, StdError::ParseErr { .. }));
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{
+    Decimal, Deps, MessageInfo, Order, StdResult, Storage, Timestamp, Uint128, Validator,
+};
+
+use crate::error::ContractError;
+use crate::state::{
+    ValidatorInfo, CONFIG, CONSUMERS, CONSUMER_STAKE, CONSUMER_WEIGHTS, PROFILES, UPTIME_OUTLIERS,
+    VALIDATORS, WEIGHT_TARGETS, WEIGHT_USAGE,
+};
+
+pub fn ensure_admin(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if info.sender != cfg.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
+/// The chain's bonded validators by operator address. `query_validator` also answers for
+/// jailed and unbonded validators the staking module still knows, so only
+/// `query_all_validators` tells whether a validator is bonded.
+pub fn bonded_validators(deps: Deps) -> StdResult<BTreeMap<String, Validator>> {
+    Ok(deps
+        .querier
+        .query_all_validators()?
+        .into_iter()
+        .map(|v| (v.address.clone(), v))
+        .collect())
+}
+
+/// Look up a validator operator address in the bonded set. `query_validator` also answers for
+/// jailed and unbonded validators the staking module still knows, so the address is matched
+/// against `query_all_validators`. Operator addresses use the `valoper` prefix, so
+/// `addr_validate` cannot be used for them.
+pub fn validate_validator(deps: Deps, address: &str) -> Result<Validator, ContractError> {
+    deps.querier
+        .query_all_validators()?
+        .into_iter()
+        .find(|v| v.address == address)
+        .ok_or_else(|| ContractError::InvalidValidator {
+            address: address.to_string(),
+        })
+}
+
+pub fn new_validator_info(
+    address: String,
+    weight: Decimal,
+    commission_rate: Decimal,
+    now: Timestamp,
+) -> ValidatorInfo {
+    ValidatorInfo {
+        address,
+        delegated_amount: Uint128::zero(),
+        weight,
+        last_reward_claim: now,
+        slashing_events: 0,
+        uptime_percentage: Decimal::percent(100),
+        commission_rate,
+        is_active: true,
+        draining: false,
+    }
+}
+
+/// Shared active validators and weights in the shape the hub consumes
+pub fn active_validator_params(deps: Deps) -> StdResult<Vec<regen_types::ValidatorParams>> {
+    VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|r| match r {
+            Ok((_, v)) if v.is_active => Some(Ok(regen_types::ValidatorParams {
+                address: v.address,
+                weight: v.weight,
+            })),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect()
+}
+
+/// Delete a validator along with its profile and per-consumer weight overrides.
+/// Its history is kept so the removal stays auditable.
+pub fn delete_validator(storage: &mut dyn Storage, address: &str) -> StdResult<()> {
+    VALIDATORS.remove(storage, address.to_string())?;
+    PROFILES.remove(storage, address.to_string());
+    UPTIME_OUTLIERS.remove(storage, address.to_string());
+    WEIGHT_TARGETS.remove(storage, address.to_string());
+    WEIGHT_USAGE.remove(storage, address.to_string());
+    let consumers = CONSUMERS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for consumer in consumers {
+        CONSUMER_WEIGHTS.remove(storage, (&consumer, address));
+        CONSUMER_STAKE.remove(storage, (&consumer, address));
+    }
+    Ok(())
+}
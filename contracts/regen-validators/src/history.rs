@@ -0,0 +1,132 @@
+use cosmwasm_std::{Deps, DepsMut, Env, Order, Response, StdResult, Storage, Timestamp};
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::msg::ValidatorHistoryResponse;
+use crate::state::{ValidatorInfo, ValidatorSnapshot, HISTORY_PARAMS, VALIDATOR_HISTORY};
+
+/// Most expired snapshots deleted by a single write, so pruning cost stays bounded
+const PRUNE_PER_WRITE: usize = 10;
+
+/// Append a snapshot of `v` to its history and prune snapshots that left the retention window.
+/// Several changes within one block keep only the last snapshot.
+pub fn record_snapshot(storage: &mut dyn Storage, v: &ValidatorInfo, now: Timestamp) -> StdResult<()> {
+    VALIDATOR_HISTORY.save(
+        storage,
+        (&v.address, now.seconds()),
+        &ValidatorSnapshot {
+            timestamp: now,
+            weight: v.weight,
+            commission_rate: v.commission_rate,
+            uptime_percentage: v.uptime_percentage,
+            slashing_events: v.slashing_events,
+            delegated_amount: v.delegated_amount,
+            is_active: v.is_active,
+        },
+    )?;
+    prune(storage, &v.address, now, PRUNE_PER_WRITE)?;
+    Ok(())
+}
+
+/// Delete up to `limit` snapshots of `address` older than the retention window
+fn prune(storage: &mut dyn Storage, address: &str, now: Timestamp, limit: usize) -> StdResult<u32> {
+    let retention = HISTORY_PARAMS.load(storage)?.retention;
+    let cutoff = now.seconds().saturating_sub(retention);
+    let expired = VALIDATOR_HISTORY
+        .prefix(address)
+        .keys(storage, None, Some(Bound::exclusive(cutoff)), Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for ts in &expired {
+        VALIDATOR_HISTORY.remove(storage, (address, *ts));
+    }
+    Ok(expired.len() as u32)
+}
+
+/// Prune the history of `address`, e.g. after it stopped receiving updates (permissionless)
+pub fn exec_prune_history(
+    deps: DepsMut,
+    env: Env,
+    address: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let pruned = prune(deps.storage, &address, env.block.time, limit)?;
+    Ok(Response::new()
+        .add_attribute("action", "prune_history")
+        .add_attribute("validator", address)
+        .add_attribute("pruned", pruned.to_string()))
+}
+
+/// Snapshots of `address` with `start <= timestamp < end` (seconds), oldest first
+pub fn query_validator_history(
+    deps: Deps,
+    address: String,
+    start: Option<u64>,
+    end: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ValidatorHistoryResponse> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let snapshots = VALIDATOR_HISTORY
+        .prefix(&address)
+        .range(
+            deps.storage,
+            start.map(Bound::inclusive),
+            end.map(Bound::exclusive),
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|r| r.map(|(_, s)| s))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ValidatorHistoryResponse { address, snapshots })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::Decimal;
+
+    use crate::helpers::new_validator_info;
+    use crate::state::HistoryParams;
+
+    fn timestamps(deps: Deps) -> Vec<u64> {
+        query_validator_history(deps, "val1".to_string(), None, None, None)
+            .unwrap()
+            .snapshots
+            .iter()
+            .map(|s| s.timestamp.seconds())
+            .collect()
+    }
+
+    #[test]
+    fn pruning_keeps_snapshots_at_the_retention_boundary() {
+        let mut deps = mock_dependencies();
+        HISTORY_PARAMS.save(deps.as_mut().storage, &HistoryParams { retention: 100 }).unwrap();
+        let v = new_validator_info(
+            "val1".to_string(),
+            Decimal::one(),
+            Decimal::percent(5),
+            Timestamp::from_seconds(0),
+        );
+        for t in [100, 149, 150, 200] {
+            record_snapshot(deps.as_mut().storage, &v, Timestamp::from_seconds(t)).unwrap();
+        }
+        assert_eq!(timestamps(deps.as_ref()), vec![100, 149, 150, 200]);
+
+        // Exactly `retention` seconds old is still inside the window
+        record_snapshot(deps.as_mut().storage, &v, Timestamp::from_seconds(250)).unwrap();
+        assert_eq!(timestamps(deps.as_ref()), vec![150, 200, 250]);
+
+        // Nothing left to prune until the window moves on
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(250);
+        let res = exec_prune_history(deps.as_mut(), env.clone(), "val1".to_string(), None).unwrap();
+        assert_eq!(res.attributes[2].value, "0");
+
+        env.block.time = Timestamp::from_seconds(301);
+        let res = exec_prune_history(deps.as_mut(), env, "val1".to_string(), Some(1)).unwrap();
+        assert_eq!(res.attributes[2].value, "1");
+        assert_eq!(timestamps(deps.as_ref()), vec![200, 250]);
+    }
+}
@@ -1,5 +1,16 @@
+pub mod error;
 pub mod state;
 pub mod msg;
 pub mod contract;
+pub mod helpers;
+pub mod scoring;
+pub mod oracle;
+pub mod applications;
+pub mod policy;
+pub mod history;
+pub mod profiles;
+pub mod consumers;
+pub mod stake;
+pub mod weights;
 
-pub use crate::contract::{execute, instantiate, query};
\ No newline at end of file
+pub use crate::contract::{execute, instantiate, migrate, query, reply};
\ No newline at end of file
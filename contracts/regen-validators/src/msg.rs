@@ -1,12 +1,38 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Decimal, Uint128};
+use cosmwasm_std::{Decimal, Timestamp, Uint128};
 
-use crate::state::ValidatorInfo;
+use regen_types::{ActiveValidatorsResponse, DelegationAmount, VotingPowerResponse};
+/// Address and weight of one validator in a whole-set weight update
+pub use regen_types::ValidatorParams as ValidatorWeight;
+
+use crate::state::{
+    Application, ApplicationRules, Attestation, EligibilityPolicy, HistoryParams, MetricsRecord,
+    MetricsSubmission, NetworkStats, OracleParams, PolicyRule, PushFailure, ScoringParams,
+    ValidatorInfo,
+    ValidatorProfile, ValidatorSnapshot, ValidatorStatus, WeightLimits,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub admin: String,
     pub validators: Vec<ValidatorParams>,
+    /// Defaults to `ScoringParams::default()` if omitted
+    pub scoring: Option<ScoringParams>,
+    pub oracle: Option<String>,
+    /// Defaults to `OracleParams::default()` if omitted
+    pub oracle_params: Option<OracleParams>,
+    /// Liquid staking hubs served by the registry
+    pub consumers: Vec<ConsumerParams>,
+    pub governance: Option<String>,
+    pub attester: Option<String>,
+    /// Defaults to `ApplicationRules::default()` if omitted
+    pub application_rules: Option<ApplicationRules>,
+    /// Defaults to `EligibilityPolicy::default()` (no rules enforced) if omitted
+    pub policy: Option<EligibilityPolicy>,
+    /// Defaults to `HistoryParams::default()` if omitted
+    pub history_params: Option<HistoryParams>,
+    /// Defaults to `WeightLimits::default()` (no limits) if omitted
+    pub weight_limits: Option<WeightLimits>,
 }
 
 #[cw_serde]
@@ -19,31 +45,243 @@ pub struct ValidatorParams {
 #[cw_serde]
 pub enum ExecuteMsg {
     AddValidator { params: ValidatorParams },
+    /// Remove a validator. If consumers delegate to it, it is deactivated and drained first.
     RemoveValidator { address: String },
+    /// Move a validator's weight towards `weight`, as far as the weight limits allow this epoch
     UpdateWeight { address: String, weight: Decimal },
     SetCommission { address: String, commission_rate: Decimal },
     Activate { address: String },
     Deactivate { address: String },
     TransferAdmin { new_admin: String },
+    UpdateScoringParams { params: ScoringParams },
+    /// Recompute weights of active validators from their performance metrics (admin only)
+    RecomputeWeights {},
+    /// Replace the weights of the whole active set in one step: every active validator exactly
+    /// once with a positive weight, optionally normalized to sum to one. Weight limits still apply.
+    SetWeights {
+        weights: Vec<ValidatorWeight>,
+        normalize: bool,
+    },
+    UpdateWeightLimits { limits: WeightLimits },
+    /// Phase queued weight targets in within the current epoch's limits (permissionless)
+    AdvanceWeights {},
+    SetOracle { oracle: Option<String> },
+    /// Register a consumer hub or replace its settings
+    SetConsumer {
+        address: String,
+        name: String,
+        /// Validators the consumer delegates to; `None` follows the whole shared active set
+        validators: Option<Vec<String>>,
+    },
+    /// Stop serving a consumer; pending removals it was still draining complete immediately
+    RemoveConsumer { address: String },
+    /// Override a validator's weight for one consumer; `None` restores the shared weight
+    SetConsumerWeight {
+        consumer: String,
+        validator: String,
+        weight: Option<Decimal>,
+    },
+    UpdateOracleParams { params: OracleParams },
+    /// Consumer callback: it holds no stake with the validator anymore. A draining validator
+    /// is deleted once every consumer that delegated to it has reported.
+    ValidatorDrained { address: String },
+    /// Consumer callback with its new delegations to the validators an operation changed
+    DelegationReport { delegations: Vec<DelegationAmount> },
+    SetGovernance { governance: Option<String> },
+    UpdateApplicationRules { rules: ApplicationRules },
+    /// Apply to join the validator set (permissionless, with the configured deposit as funds)
+    SubmitApplication { application: ApplicationParams },
+    /// Withdraw a pending application and refund its deposit (applicant only)
+    WithdrawApplication { address: String },
+    /// Approve a pending application and add the validator (admin or governance)
+    ApproveApplication { address: String, weight: Decimal },
+    /// Reject a pending application and refund its deposit (admin or governance)
+    RejectApplication { address: String, reason: String },
+    /// Refresh commission from the chain and deactivate validators that left the bonded set (permissionless)
+    SyncValidators {},
+    UpdatePolicy { policy: EligibilityPolicy },
+    /// Check every validator against the eligibility policy and deactivate those
+    /// in violation for longer than the grace period (permissionless)
+    EvaluatePolicy {},
+    UpdateHistoryParams { params: HistoryParams },
+    /// Delete up to `limit` snapshots of `address` older than the retention window (permissionless)
+    PruneHistory { address: String, limit: Option<u32> },
+    SetAttester { attester: Option<String> },
+    /// Assign the account allowed to edit a validator's profile (admin only)
+    SetProfileManager { address: String, manager: String },
+    /// Replace a validator's profile (profile manager only); clears any verification
+    UpdateProfile { address: String, profile: ProfileParams },
+    /// Mark a profile's attestations as verified or revoke that mark (attester only)
+    SetProfileVerified { address: String, verified: bool },
+    /// Submit a batch of validator metrics for an epoch (oracle only)
+    SubmitMetrics {
+        epoch: u64,
+        metrics: Vec<ValidatorMetrics>,
+        /// Total bonded tokens on the network, used to size voting power shares
+        bonded_tokens: Option<Uint128>,
+    },
 }
 
+#[cw_serde]
+pub struct ConsumerParams {
+    pub address: String,
+    pub name: String,
+    pub validators: Option<Vec<String>>,
+}
+
+#[cw_serde]
+pub struct ApplicationParams {
+    /// Validator operator address
+    pub address: String,
+    pub moniker: String,
+    pub website: Option<String>,
+    pub details: Option<String>,
+    pub commission_commitment: Decimal,
+}
+
+#[cw_serde]
+pub struct ProfileParams {
+    pub moniker: String,
+    pub website: Option<String>,
+    pub details: Option<String>,
+    pub region: Option<String>,
+    pub hosting: Option<String>,
+    pub attestations: Vec<Attestation>,
+}
+
+#[cw_serde]
+pub struct ValidatorMetrics {
+    pub address: String,
+    pub uptime_percentage: Decimal,
+    pub missed_blocks: u64,
+    /// Cumulative slashing events observed on chain
+    pub slashing_events: u32,
+    /// Share of total network voting power
+    pub voting_power: Option<Decimal>,
+    /// Operator self-delegation in uregen
+    pub self_bond: Option<Uint128>,
+    /// Position by voting power in the bonded set, 1 being the largest
+    pub voting_power_rank: Option<u32>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
     #[returns(ConfigResponse)]
     Config {},
+    /// Validators matching every given filter, by address or by descending weight or stake.
+    /// `start_after` is the last address of the previous page.
     #[returns(ValidatorsResponse)]
-    Validators {},
+    Validators {
+        status: Option<ValidatorStatus>,
+        min_weight: Option<Decimal>,
+        /// Only validators whose profile carries an attestation of this kind
+        attestation: Option<String>,
+        order_by: Option<ValidatorOrder>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(ValidatorResponse)]
     Validator { address: String },
+    /// Shared active validators and weights
+    #[returns(ActiveValidatorsResponse)]
+    ActiveValidators {},
+    #[returns(ConsumersResponse)]
+    Consumers {},
+    #[returns(ConsumerResponse)]
+    Consumer { address: String },
+    /// A consumer's own view of the active set, with its restrictions and weight overrides
+    #[returns(ActiveValidatorsResponse)]
+    ConsumerValidators { consumer: String },
+    /// Network voting power share and rank of validators with fresh oracle metrics
+    #[returns(VotingPowerResponse)]
+    VotingPower {},
+    #[returns(Option<NetworkStats>)]
+    NetworkStats {},
+    /// Total stake reported by consumers, its concentration and each consumer's share
+    #[returns(StakeSummaryResponse)]
+    StakeSummary {},
+    /// A validator's stake broken down by consumer
+    #[returns(ValidatorStakeResponse)]
+    ValidatorStake { address: String },
+    #[returns(ScoringParams)]
+    ScoringParams {},
+    /// Score breakdown and resulting weight for every validator
+    #[returns(ScoreBreakdownResponse)]
+    ScoreBreakdown {},
+    #[returns(OracleParams)]
+    OracleParams {},
+    #[returns(ValidatorMetricsResponse)]
+    ValidatorMetrics { address: String },
+    #[returns(ApplicationRules)]
+    ApplicationRules {},
+    #[returns(ApplicationResponse)]
+    Application { address: String },
+    /// Pending applications in address order
+    #[returns(ApplicationsResponse)]
+    Applications {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(EligibilityPolicy)]
+    Policy {},
+    /// Every policy check for every validator, with any open violation
+    #[returns(PolicyReportResponse)]
+    PolicyReport {},
+    /// Validators whose weight is still being phased in towards a target
+    #[returns(WeightScheduleResponse)]
+    WeightSchedule {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(HistoryParams)]
+    HistoryParams {},
+    /// Snapshots of a validator with `start <= timestamp < end` (seconds), oldest first
+    #[returns(ValidatorHistoryResponse)]
+    ValidatorHistory {
+        address: String,
+        start: Option<u64>,
+        end: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(ProfileResponse)]
+    Profile { address: String },
+    /// Profiles in address order, optionally only those with an attestation of `attestation`
+    /// kind and/or the given verification status
+    #[returns(ProfilesResponse)]
+    Profiles {
+        attestation: Option<String>,
+        verified: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Return recent metrics submissions (descending epoch)
+    #[returns(MetricsSubmissionsResponse)]
+    MetricsSubmissions {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[cw_serde]
 pub struct ConfigResponse {
     pub admin: String,
+    pub oracle: Option<String>,
+    pub governance: Option<String>,
+    pub attester: Option<String>,
     pub total_active: u32,
 }
 
+#[cw_serde]
+pub enum ValidatorOrder {
+    Address,
+    Weight,
+    DelegatedAmount,
+}
+
 #[cw_serde]
 pub struct ValidatorsResponse {
     pub validators: Vec<ValidatorInfo>,
@@ -52,4 +290,158 @@ pub struct ValidatorsResponse {
 #[cw_serde]
 pub struct ValidatorResponse {
     pub validator: Option<ValidatorInfo>,
-}
\ No newline at end of file
+}
+#[cw_serde]
+pub struct ValidatorScore {
+    pub address: String,
+    pub uptime_score: Decimal,
+    pub commission_score: Decimal,
+    pub slashing_penalty: Decimal,
+    pub score: Decimal,
+    pub eligible: bool,
+    pub weight: Decimal,
+}
+
+#[cw_serde]
+pub struct ScoreBreakdownResponse {
+    pub scores: Vec<ValidatorScore>,
+}
+
+#[cw_serde]
+pub struct ValidatorMetricsResponse {
+    pub metrics: Option<MetricsRecord>,
+    /// True if the metrics are missing or older than `max_staleness` while an oracle is configured
+    pub stale: bool,
+}
+
+#[cw_serde]
+pub struct MetricsSubmissionsResponse {
+    pub submissions: Vec<MetricsSubmission>,
+}
+
+#[cw_serde]
+pub struct ApplicationResponse {
+    pub application: Option<Application>,
+}
+
+#[cw_serde]
+pub struct ApplicationsResponse {
+    pub applications: Vec<Application>,
+}
+
+#[cw_serde]
+pub struct PolicyCheck {
+    pub rule: PolicyRule,
+    pub passed: bool,
+    /// Observed value against the limit, e.g. "commission 0.25 > 0.2"
+    pub detail: String,
+}
+
+#[cw_serde]
+pub struct ValidatorPolicyReport {
+    pub address: String,
+    pub passed: bool,
+    pub checks: Vec<PolicyCheck>,
+    /// Start of the open violation, if any
+    pub violation_since: Option<Timestamp>,
+    /// When an active validator in violation becomes eligible for deactivation
+    pub deactivate_at: Option<Timestamp>,
+}
+
+#[cw_serde]
+pub struct PolicyReportResponse {
+    pub validators: Vec<ValidatorPolicyReport>,
+}
+
+#[cw_serde]
+pub struct WeightScheduleEntry {
+    pub address: String,
+    pub weight: Decimal,
+    pub target: Decimal,
+    /// Epochs needed at the per-validator limit; `None` without one
+    pub epochs_remaining: Option<u64>,
+}
+
+#[cw_serde]
+pub struct WeightScheduleResponse {
+    pub limits: WeightLimits,
+    pub epoch: u64,
+    /// Set-wide change still allowed in the current epoch
+    pub set_remaining: Option<Decimal>,
+    pub entries: Vec<WeightScheduleEntry>,
+}
+
+#[cw_serde]
+pub struct ValidatorHistoryResponse {
+    pub address: String,
+    pub snapshots: Vec<ValidatorSnapshot>,
+}
+
+#[cw_serde]
+pub struct ProfileResponse {
+    pub profile: Option<ValidatorProfile>,
+}
+
+#[cw_serde]
+pub struct ProfileEntry {
+    pub address: String,
+    pub profile: ValidatorProfile,
+}
+
+#[cw_serde]
+pub struct ProfilesResponse {
+    pub profiles: Vec<ProfileEntry>,
+}
+
+#[cw_serde]
+pub struct WeightOverride {
+    pub validator: String,
+    pub weight: Decimal,
+}
+
+#[cw_serde]
+pub struct ConsumerResponse {
+    pub address: String,
+    pub name: String,
+    pub validators: Option<Vec<String>>,
+    pub weight_overrides: Vec<WeightOverride>,
+    pub last_push_failure: Option<PushFailure>,
+}
+
+#[cw_serde]
+pub struct ConsumersResponse {
+    pub consumers: Vec<ConsumerResponse>,
+}
+
+#[cw_serde]
+pub struct ConsumerStake {
+    pub consumer: String,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct ValidatorShare {
+    pub address: String,
+    pub amount: Uint128,
+    pub share: Decimal,
+}
+
+#[cw_serde]
+pub struct StakeSummaryResponse {
+    pub total_delegated: Uint128,
+    /// Validators holding any stake
+    pub staked_validators: u32,
+    pub largest: Option<ValidatorShare>,
+    /// Herfindahl-Hirschman index of the stake distribution, from 1/n (even) to 1
+    pub hhi: Decimal,
+    /// Fewest validators holding more than a third of the stake
+    pub nakamoto_coefficient: u32,
+    pub consumers: Vec<ConsumerStake>,
+}
+
+#[cw_serde]
+pub struct ValidatorStakeResponse {
+    pub address: String,
+    pub delegated_amount: Uint128,
+    pub consumers: Vec<ConsumerStake>,
+}
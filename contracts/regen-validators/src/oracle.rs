@@ -0,0 +1,255 @@
+use std::collections::BTreeSet;
+
+use cosmwasm_std::{
+    Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage,
+    Timestamp, Uint128,
+};
+use cw_storage_plus::Bound;
+use regen_types::{ValidatorVotingPower, VotingPowerResponse};
+
+use crate::error::ContractError;
+use crate::history::record_snapshot;
+use crate::msg::{MetricsSubmissionsResponse, ValidatorMetrics, ValidatorMetricsResponse};
+use crate::state::{
+    Config, MetricsRecord, MetricsSubmission, NetworkStats, OracleParams, RejectedMetrics,
+    UptimeOutlier, CONFIG, METRICS_SUBMISSIONS, NETWORK_STATS, ORACLE_PARAMS, UPTIME_OUTLIERS,
+    VALIDATORS, VALIDATOR_METRICS,
+};
+
+/// Whether a validator's metrics are too old to be trusted. Without an oracle the
+/// admin-maintained values are used as-is and are never considered stale.
+pub fn is_stale(
+    cfg: &Config,
+    params: &OracleParams,
+    record: Option<&MetricsRecord>,
+    now: Timestamp,
+) -> bool {
+    if cfg.oracle.is_none() {
+        return false;
+    }
+    match record {
+        Some(r) => now.seconds().saturating_sub(r.updated_at.seconds()) > params.max_staleness,
+        None => true,
+    }
+}
+
+pub fn exec_submit_metrics(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epoch: u64,
+    metrics: Vec<ValidatorMetrics>,
+    bonded_tokens: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if cfg.oracle.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if metrics.is_empty() {
+        return Err(ContractError::Std(StdError::generic_err("empty metrics batch")));
+    }
+    let last_epoch = METRICS_SUBMISSIONS
+        .keys(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?;
+    if let Some(last) = last_epoch {
+        if epoch <= last {
+            return Err(ContractError::Std(StdError::generic_err(format!(
+                "epoch {} is not newer than last submitted epoch {}",
+                epoch, last
+            ))));
+        }
+    }
+
+    let params = ORACLE_PARAMS.load(deps.storage)?;
+    let mut seen = BTreeSet::new();
+    let mut accepted = 0u32;
+    let mut rejected = Vec::new();
+
+    for m in metrics {
+        if !seen.insert(m.address.clone()) {
+            rejected.push(RejectedMetrics {
+                address: m.address,
+                reason: "duplicate entry".to_string(),
+            });
+            continue;
+        }
+        let prev = match check_metrics(deps.as_ref(), &m) {
+            Ok(prev) => prev,
+            Err(reason) => {
+                rejected.push(RejectedMetrics {
+                    address: m.address,
+                    reason,
+                });
+                continue;
+            }
+        };
+        let change = prev.map_or(Decimal::zero(), |p| {
+            p.uptime_percentage.abs_diff(m.uptime_percentage)
+        });
+        if change > params.max_uptime_change && !confirm_outlier(deps.storage, &params, &m)? {
+            rejected.push(RejectedMetrics {
+                address: m.address,
+                reason: format!("uptime moved by {} since last epoch", change),
+            });
+            continue;
+        }
+        UPTIME_OUTLIERS.remove(deps.storage, m.address.clone());
+
+        VALIDATOR_METRICS.save(
+            deps.storage,
+            m.address.clone(),
+            &MetricsRecord {
+                epoch,
+                uptime_percentage: m.uptime_percentage,
+                missed_blocks: m.missed_blocks,
+                slashing_events: m.slashing_events,
+                voting_power: m.voting_power,
+                self_bond: m.self_bond,
+                voting_power_rank: m.voting_power_rank,
+                updated_at: env.block.time,
+            },
+        )?;
+        let v = VALIDATORS.update(deps.storage, m.address.clone(), |maybe| match maybe {
+            Some(mut v) => {
+                v.uptime_percentage = m.uptime_percentage;
+                v.slashing_events = m.slashing_events;
+                Ok(v)
+            }
+            None => Err(StdError::not_found("ValidatorInfo")),
+        })?;
+        record_snapshot(deps.storage, &v, env.block.time)?;
+        accepted += 1;
+    }
+
+    if let Some(bonded_tokens) = bonded_tokens {
+        NETWORK_STATS.save(
+            deps.storage,
+            &NetworkStats {
+                epoch,
+                bonded_tokens,
+                updated_at: env.block.time,
+            },
+        )?;
+    }
+
+    let rejected_count = rejected.len();
+    METRICS_SUBMISSIONS.save(
+        deps.storage,
+        epoch,
+        &MetricsSubmission {
+            epoch,
+            submitter: info.sender,
+            submitted_at: env.block.time,
+            accepted,
+            rejected,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "submit_metrics")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("accepted", accepted.to_string())
+        .add_attribute("rejected", rejected_count.to_string()))
+}
+
+/// Validate one entry against the registry and the previous report, returning the previous
+/// report or the rejection reason. Uptime moves are checked by the caller.
+fn check_metrics(deps: Deps, m: &ValidatorMetrics) -> Result<Option<MetricsRecord>, String> {
+    if !VALIDATORS.has(deps.storage, m.address.clone()) {
+        return Err("unknown validator".to_string());
+    }
+    if m.uptime_percentage > Decimal::one() {
+        return Err("uptime above 100%".to_string());
+    }
+    if m.voting_power.map_or(false, |vp| vp > Decimal::one()) {
+        return Err("voting power above 100%".to_string());
+    }
+    let prev = VALIDATOR_METRICS
+        .may_load(deps.storage, m.address.clone())
+        .map_err(|e| e.to_string())?;
+    if let Some(prev) = &prev {
+        if m.slashing_events < prev.slashing_events {
+            return Err("slashing events decreased".to_string());
+        }
+    }
+    Ok(prev)
+}
+
+/// Count an uptime report too far from the last accepted one. Returns whether
+/// `outlier_confirmations` consecutive reports now agree on the new level, so a sustained
+/// change is accepted instead of leaving the validator's metrics stale for good.
+fn confirm_outlier(
+    storage: &mut dyn Storage,
+    params: &OracleParams,
+    m: &ValidatorMetrics,
+) -> StdResult<bool> {
+    let reports = match UPTIME_OUTLIERS.may_load(storage, m.address.clone())? {
+        Some(o)
+            if o.uptime_percentage.abs_diff(m.uptime_percentage) <= params.max_uptime_change =>
+        {
+            o.reports + 1
+        }
+        _ => 1,
+    };
+    if reports >= params.outlier_confirmations {
+        return Ok(true);
+    }
+    UPTIME_OUTLIERS.save(
+        storage,
+        m.address.clone(),
+        &UptimeOutlier {
+            uptime_percentage: m.uptime_percentage,
+            reports,
+        },
+    )?;
+    Ok(false)
+}
+
+pub fn query_validator_metrics(deps: Deps, env: Env, address: String) -> StdResult<ValidatorMetricsResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let params = ORACLE_PARAMS.load(deps.storage)?;
+    let metrics = VALIDATOR_METRICS.may_load(deps.storage, address)?;
+    let stale = is_stale(&cfg, &params, metrics.as_ref(), env.block.time);
+    Ok(ValidatorMetricsResponse { metrics, stale })
+}
+
+/// Voting power of every validator whose metrics are fresh; stale reports are left out so
+/// consumers never size allocations on outdated figures
+pub fn query_voting_power(deps: Deps, env: Env) -> StdResult<VotingPowerResponse> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let params = ORACLE_PARAMS.load(deps.storage)?;
+    let validators = VALIDATOR_METRICS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|r| match r {
+            Ok((address, m)) if !is_stale(&cfg, &params, Some(&m), env.block.time) => {
+                Some(Ok(ValidatorVotingPower {
+                    address,
+                    voting_power: m.voting_power,
+                    rank: m.voting_power_rank,
+                }))
+            }
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(VotingPowerResponse {
+        bonded_tokens: NETWORK_STATS.may_load(deps.storage)?.map(|s| s.bonded_tokens),
+        validators,
+    })
+}
+
+pub fn query_metrics_submissions(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<MetricsSubmissionsResponse> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let end = start_after.map(Bound::exclusive);
+    let submissions = METRICS_SUBMISSIONS
+        .range(deps.storage, None, end, Order::Descending)
+        .take(limit)
+        .map(|r| r.map(|(_, v)| v))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(MetricsSubmissionsResponse { submissions })
+}
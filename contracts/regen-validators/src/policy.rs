@@ -0,0 +1,263 @@
+use cosmwasm_std::{Decimal, Deps, DepsMut, Env, Event, Order, Response, StdError, StdResult};
+
+use crate::error::ContractError;
+use crate::consumers::consumer_update_msgs;
+use crate::history::record_snapshot;
+use crate::msg::{PolicyCheck, PolicyReportResponse, ValidatorPolicyReport};
+use crate::state::{
+    EligibilityPolicy, MetricsRecord, PolicyRule, PolicyViolation, ValidatorInfo, POLICY,
+    POLICY_VIOLATIONS, VALIDATORS, VALIDATOR_METRICS,
+};
+
+/// Run every enabled rule of `policy` against a validator. Rules backed by oracle data
+/// fail when the oracle has not reported the value.
+pub fn check_policy(
+    policy: &EligibilityPolicy,
+    v: &ValidatorInfo,
+    metrics: Option<&MetricsRecord>,
+) -> Vec<PolicyCheck> {
+    let mut checks = Vec::new();
+    if let Some(max) = policy.max_commission {
+        checks.push(upper_bound(PolicyRule::MaxCommission, "commission", v.commission_rate, max));
+    }
+    if let Some(min) = policy.min_uptime {
+        checks.push(lower_bound(PolicyRule::MinUptime, "uptime", v.uptime_percentage, min));
+    }
+    if let Some(max) = policy.max_slashing_events {
+        checks.push(upper_bound(PolicyRule::MaxSlashingEvents, "slashing events", v.slashing_events, max));
+    }
+    if let Some(max) = policy.max_voting_power {
+        checks.push(match metrics.and_then(|m| m.voting_power) {
+            Some(vp) => upper_bound(PolicyRule::MaxVotingPower, "voting power", vp, max),
+            None => not_reported(PolicyRule::MaxVotingPower, "voting power"),
+        });
+    }
+    if let Some(min) = policy.min_self_bond {
+        checks.push(match metrics.and_then(|m| m.self_bond) {
+            Some(bond) => lower_bound(PolicyRule::MinSelfBond, "self bond", bond, min),
+            None => not_reported(PolicyRule::MinSelfBond, "self bond"),
+        });
+    }
+    checks
+}
+
+fn upper_bound<T: PartialOrd + ToString>(rule: PolicyRule, what: &str, value: T, max: T) -> PolicyCheck {
+    let passed = value <= max;
+    let op = if passed { "<=" } else { ">" };
+    PolicyCheck {
+        rule,
+        passed,
+        detail: format!("{} {} {} {}", what, value.to_string(), op, max.to_string()),
+    }
+}
+
+fn lower_bound<T: PartialOrd + ToString>(rule: PolicyRule, what: &str, value: T, min: T) -> PolicyCheck {
+    let passed = value >= min;
+    let op = if passed { ">=" } else { "<" };
+    PolicyCheck {
+        rule,
+        passed,
+        detail: format!("{} {} {} {}", what, value.to_string(), op, min.to_string()),
+    }
+}
+
+fn not_reported(rule: PolicyRule, what: &str) -> PolicyCheck {
+    PolicyCheck {
+        rule,
+        passed: false,
+        detail: format!("{} not reported by the oracle", what),
+    }
+}
+
+fn failed_rules(checks: &[PolicyCheck]) -> Vec<PolicyRule> {
+    checks.iter().filter(|c| !c.passed).map(|c| c.rule).collect()
+}
+
+fn rule_names(rules: &[PolicyRule]) -> String {
+    rules
+        .iter()
+        .map(|r| r.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn exec_evaluate_policy(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let policy = POLICY.load(deps.storage)?;
+    let validators = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| r.map(|(_, v)| v))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let now = env.block.time;
+    let mut events = Vec::new();
+    let mut violations = 0u32;
+    let mut deactivated = 0u32;
+    for mut v in validators {
+        if v.draining {
+            continue;
+        }
+        let metrics = VALIDATOR_METRICS.may_load(deps.storage, v.address.clone())?;
+        let failed = failed_rules(&check_policy(&policy, &v, metrics.as_ref()));
+        let existing = POLICY_VIOLATIONS.may_load(deps.storage, v.address.clone())?;
+
+        if failed.is_empty() {
+            if existing.is_some() {
+                POLICY_VIOLATIONS.remove(deps.storage, v.address.clone());
+                events.push(Event::new("validator_policy_cleared").add_attribute("validator", &v.address));
+            }
+            continue;
+        }
+
+        violations += 1;
+        let since = existing.as_ref().map_or(now, |e| e.since);
+        if existing.as_ref().map(|e| &e.rules) != Some(&failed) {
+            events.push(
+                Event::new("validator_policy_violation")
+                    .add_attribute("validator", &v.address)
+                    .add_attribute("rules", rule_names(&failed))
+                    .add_attribute("since", since.seconds().to_string()),
+            );
+            POLICY_VIOLATIONS.save(
+                deps.storage,
+                v.address.clone(),
+                &PolicyViolation { rules: failed.clone(), since },
+            )?;
+        }
+
+        if v.is_active && now >= since.plus_seconds(policy.grace_period) {
+            v.is_active = false;
+            VALIDATORS.save(deps.storage, v.address.clone(), &v)?;
+            record_snapshot(deps.storage, &v, now)?;
+            events.push(
+                Event::new("validator_deactivated")
+                    .add_attribute("validator", &v.address)
+                    .add_attribute("reason", "policy")
+                    .add_attribute("rules", rule_names(&failed)),
+            );
+            deactivated += 1;
+        }
+    }
+
+    let mut res = Response::new();
+    if deactivated > 0 {
+        res = res.add_submessages(consumer_update_msgs(deps)?);
+    }
+    Ok(res
+        .add_attribute("action", "evaluate_policy")
+        .add_attribute("violations", violations.to_string())
+        .add_attribute("deactivated", deactivated.to_string())
+        .add_events(events))
+}
+
+pub fn exec_update_policy(
+    deps: DepsMut,
+    policy: EligibilityPolicy,
+) -> Result<Response, ContractError> {
+    if policy.max_commission.map_or(false, |c| c > Decimal::one())
+        || policy.min_uptime.map_or(false, |u| u > Decimal::one())
+    {
+        return Err(ContractError::Std(StdError::generic_err(
+            "policy percentages cannot exceed 100%",
+        )));
+    }
+    POLICY.save(deps.storage, &policy)?;
+    Ok(Response::new().add_attribute("action", "update_policy"))
+}
+
+pub fn query_policy_report(deps: Deps) -> StdResult<PolicyReportResponse> {
+    let policy = POLICY.load(deps.storage)?;
+    let validators = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|r| {
+            let (addr, v) = r?;
+            let metrics = VALIDATOR_METRICS.may_load(deps.storage, addr.clone())?;
+            let checks = check_policy(&policy, &v, metrics.as_ref());
+            let violation = POLICY_VIOLATIONS.may_load(deps.storage, addr.clone())?;
+            Ok(ValidatorPolicyReport {
+                address: addr,
+                passed: checks.iter().all(|c| c.passed),
+                checks,
+                violation_since: violation.as_ref().map(|e| e.since),
+                deactivate_at: violation
+                    .filter(|_| v.is_active)
+                    .map(|e| e.since.plus_seconds(policy.grace_period)),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(PolicyReportResponse { validators })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::{Timestamp, Uint128};
+
+    use crate::helpers::new_validator_info;
+
+    #[test]
+    fn test_check_policy() {
+        let mut v = new_validator_info("val1".to_string(), Decimal::zero(), Decimal::percent(10), Timestamp::from_seconds(0));
+        v.slashing_events = 2;
+
+        // No rules configured
+        assert!(check_policy(&EligibilityPolicy::default(), &v, None).is_empty());
+
+        let policy = EligibilityPolicy {
+            max_commission: Some(Decimal::percent(5)),
+            min_uptime: Some(Decimal::percent(95)),
+            max_slashing_events: Some(2),
+            max_voting_power: Some(Decimal::percent(10)),
+            min_self_bond: Some(Uint128::new(1_000)),
+            ..EligibilityPolicy::default()
+        };
+        let checks = check_policy(&policy, &v, None);
+        assert_eq!(
+            failed_rules(&checks),
+            vec![PolicyRule::MaxCommission, PolicyRule::MaxVotingPower, PolicyRule::MinSelfBond]
+        );
+        assert_eq!(checks[0].detail, "commission 0.1 > 0.05");
+        assert_eq!(checks[3].detail, "voting power not reported by the oracle");
+
+        let metrics = MetricsRecord {
+            epoch: 1,
+            uptime_percentage: Decimal::one(),
+            missed_blocks: 0,
+            slashing_events: 2,
+            voting_power: Some(Decimal::percent(12)),
+            self_bond: Some(Uint128::new(1_000)),
+            voting_power_rank: Some(3),
+            updated_at: Timestamp::from_seconds(0),
+        };
+        let checks = check_policy(&policy, &v, Some(&metrics));
+        assert_eq!(failed_rules(&checks), vec![PolicyRule::MaxCommission, PolicyRule::MaxVotingPower]);
+        assert_eq!(rule_names(&failed_rules(&checks)), "max_commission,max_voting_power");
+    }
+
+    #[test]
+    fn test_update_policy_rejects_percentages_above_one() {
+        let mut deps = mock_dependencies();
+        let valid = EligibilityPolicy {
+            max_commission: Some(Decimal::percent(10)),
+            min_uptime: Some(Decimal::percent(95)),
+            ..EligibilityPolicy::default()
+        };
+        exec_update_policy(deps.as_mut(), valid.clone()).unwrap();
+
+        for invalid in [
+            EligibilityPolicy {
+                max_commission: Some(Decimal::percent(101)),
+                ..valid.clone()
+            },
+            EligibilityPolicy {
+                min_uptime: Some(Decimal::percent(101)),
+                ..valid.clone()
+            },
+        ] {
+            let err = exec_update_policy(deps.as_mut(), invalid).unwrap_err();
+            assert!(err.to_string().contains("cannot exceed 100%"));
+        }
+        // The rejected updates leave the stored policy untouched
+        assert_eq!(POLICY.load(deps.as_ref().storage).unwrap(), valid);
+    }
+}
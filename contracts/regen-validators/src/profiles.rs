@@ -0,0 +1,246 @@
+use cosmwasm_std::{
+    Addr, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage,
+    Timestamp,
+};
+use cw_storage_plus::Bound;
+
+use crate::error::ContractError;
+use crate::msg::{ProfileEntry, ProfileParams, ProfileResponse, ProfilesResponse};
+use crate::state::{ValidatorProfile, Verification, CONFIG, PROFILES, VALIDATORS};
+
+const MAX_ATTESTATIONS: usize = 16;
+
+/// Create an unverified profile without region, hosting or attestations
+pub fn init_profile(
+    storage: &mut dyn Storage,
+    address: &str,
+    manager: Addr,
+    moniker: String,
+    website: Option<String>,
+    details: Option<String>,
+    now: Timestamp,
+) -> StdResult<()> {
+    PROFILES.save(
+        storage,
+        address.to_string(),
+        &ValidatorProfile {
+            manager,
+            moniker,
+            website,
+            details,
+            region: None,
+            hosting: None,
+            attestations: vec![],
+            verification: None,
+            updated_at: now,
+        },
+    )
+}
+
+pub fn exec_set_profile_manager(
+    deps: DepsMut,
+    env: Env,
+    address: String,
+    manager: String,
+) -> Result<Response, ContractError> {
+    if !VALIDATORS.has(deps.storage, address.clone()) {
+        return Err(ContractError::Std(StdError::not_found("ValidatorInfo")));
+    }
+    let manager = deps.api.addr_validate(&manager)?;
+    match PROFILES.may_load(deps.storage, address.clone())? {
+        Some(mut profile) => {
+            profile.manager = manager.clone();
+            PROFILES.save(deps.storage, address.clone(), &profile)?;
+        }
+        None => init_profile(
+            deps.storage,
+            &address,
+            manager.clone(),
+            address.clone(),
+            None,
+            None,
+            env.block.time,
+        )?,
+    }
+    Ok(Response::new()
+        .add_attribute("action", "set_profile_manager")
+        .add_attribute("validator", address)
+        .add_attribute("manager", manager))
+}
+
+pub fn exec_update_profile(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    params: ProfileParams,
+) -> Result<Response, ContractError> {
+    let profile = load_profile(deps.as_ref(), &address)?;
+    if profile.manager != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if params.moniker.is_empty() {
+        return Err(ContractError::Std(StdError::generic_err("moniker cannot be empty")));
+    }
+    if params.attestations.len() > MAX_ATTESTATIONS {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "at most {} attestations per profile",
+            MAX_ATTESTATIONS
+        ))));
+    }
+    if params.attestations.iter().any(|a| a.kind.is_empty() || a.iri.is_empty()) {
+        return Err(ContractError::Std(StdError::generic_err(
+            "attestation kind and iri cannot be empty",
+        )));
+    }
+
+    PROFILES.save(
+        deps.storage,
+        address.clone(),
+        &ValidatorProfile {
+            manager: profile.manager,
+            moniker: params.moniker,
+            website: params.website,
+            details: params.details,
+            region: params.region,
+            hosting: params.hosting,
+            attestations: params.attestations,
+            // Changed claims need to be verified again
+            verification: None,
+            updated_at: env.block.time,
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("action", "update_profile")
+        .add_attribute("validator", address))
+}
+
+pub fn exec_set_profile_verified(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    verified: bool,
+) -> Result<Response, ContractError> {
+    if CONFIG.load(deps.storage)?.attester.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut profile = load_profile(deps.as_ref(), &address)?;
+    profile.verification = verified.then_some(Verification {
+        attester: info.sender,
+        verified_at: env.block.time,
+    });
+    PROFILES.save(deps.storage, address.clone(), &profile)?;
+    Ok(Response::new()
+        .add_attribute("action", "set_profile_verified")
+        .add_attribute("validator", address)
+        .add_attribute("verified", verified.to_string()))
+}
+
+fn load_profile(deps: Deps, address: &str) -> StdResult<ValidatorProfile> {
+    PROFILES
+        .may_load(deps.storage, address.to_string())?
+        .ok_or_else(|| StdError::not_found("ValidatorProfile"))
+}
+
+pub fn query_profile(deps: Deps, address: String) -> StdResult<ProfileResponse> {
+    Ok(ProfileResponse {
+        profile: PROFILES.may_load(deps.storage, address)?,
+    })
+}
+
+pub fn query_profiles(
+    deps: Deps,
+    attestation: Option<String>,
+    verified: Option<bool>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ProfilesResponse> {
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let profiles = PROFILES
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|r| match r {
+            Ok((_, p)) => {
+                attestation
+                    .as_ref()
+                    .map_or(true, |kind| p.attestations.iter().any(|a| &a.kind == kind))
+                    && verified.map_or(true, |v| p.verification.is_some() == v)
+            }
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|r| r.map(|(address, profile)| ProfileEntry { address, profile }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ProfilesResponse { profiles })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+
+    use crate::state::{Attestation, Config};
+
+    fn params(moniker: &str, attestations: usize) -> ProfileParams {
+        ProfileParams {
+            moniker: moniker.to_string(),
+            website: None,
+            details: None,
+            region: None,
+            hosting: None,
+            attestations: (0..attestations)
+                .map(|i| Attestation {
+                    kind: "renewable_energy".to_string(),
+                    iri: format!("regen:13toVh{}.rdf", i),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn profile_changes_are_rejected_for_missing_profiles_and_other_senders() {
+        let mut deps = mock_dependencies();
+        CONFIG
+            .save(
+                deps.as_mut().storage,
+                &Config {
+                    admin: Addr::unchecked("admin"),
+                    oracle: None,
+                    governance: None,
+                    attester: Some(Addr::unchecked("attester")),
+                },
+            )
+            .unwrap();
+        let update = |deps: DepsMut, sender: &str, address: &str, params: ProfileParams| {
+            let info = mock_info(sender, &[]);
+            exec_update_profile(deps, mock_env(), info, address.to_string(), params)
+        };
+        let verify = |deps: DepsMut, sender: &str| {
+            let info = mock_info(sender, &[]);
+            exec_set_profile_verified(deps, mock_env(), info, "val1".to_string(), true)
+        };
+
+        let err = update(deps.as_mut(), "operator", "val1", params("Val 1", 0)).unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+        let err = verify(deps.as_mut(), "attester").unwrap_err();
+        assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+
+        let now = mock_env().block.time;
+        let operator = Addr::unchecked("operator");
+        init_profile(deps.as_mut().storage, "val1", operator, "val1".to_string(), None, None, now)
+            .unwrap();
+        let err = update(deps.as_mut(), "intruder", "val1", params("Val 1", 0)).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        update(deps.as_mut(), "operator", "val1", params("", 0)).unwrap_err();
+        let many = params("Val 1", MAX_ATTESTATIONS + 1);
+        update(deps.as_mut(), "operator", "val1", many).unwrap_err();
+
+        // Only the attester verifies, not the manager
+        let err = verify(deps.as_mut(), "operator").unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let profile = PROFILES.load(deps.as_ref().storage, "val1".to_string()).unwrap();
+        assert_eq!(profile.moniker, "val1");
+        assert_eq!(profile.verification, None);
+    }
+}
@@ -0,0 +1,180 @@
+use cosmwasm_std::Decimal;
+
+use crate::msg::ValidatorScore;
+use crate::state::{ScoringParams, ValidatorInfo};
+
+/// Score a single validator from its stored performance metrics. `excluded` validators
+/// (stale metrics or an open policy violation) are ineligible. The returned `weight` is
+/// left at zero; see [`assign_weights`].
+pub fn score_validator(params: &ScoringParams, v: &ValidatorInfo, excluded: bool) -> ValidatorScore {
+    let uptime_score = params.uptime_coefficient * v.uptime_percentage.min(Decimal::one());
+    let commission_score =
+        params.commission_coefficient * Decimal::one().saturating_sub(v.commission_rate);
+    let slashing_penalty = params
+        .slashing_penalty
+        .saturating_mul(Decimal::from_ratio(v.slashing_events, 1u32))
+        .min(Decimal::one());
+
+    let eligible = v.is_active
+        && !excluded
+        && v.uptime_percentage >= params.min_uptime
+        && v.commission_rate <= params.max_commission;
+    let score = if eligible {
+        (uptime_score + commission_score) * (Decimal::one() - slashing_penalty)
+    } else {
+        Decimal::zero()
+    };
+
+    ValidatorScore {
+        address: v.address.clone(),
+        uptime_score,
+        commission_score,
+        slashing_penalty,
+        score,
+        eligible,
+        weight: Decimal::zero(),
+    }
+}
+
+/// Fill in each score's weight as its share of the total score, with every validator that
+/// has a positive score raised to `min_weight` and the remaining mass shared by the rest in
+/// proportion to their scores. Weights always sum to one; when the floor cannot be met for
+/// every validator they are weighted equally.
+pub fn assign_weights(params: &ScoringParams, scores: &mut [ValidatorScore]) {
+    let total: Decimal = scores.iter().map(|s| s.score).sum();
+    if total.is_zero() {
+        return;
+    }
+    let positive = scores.iter().filter(|s| !s.score.is_zero()).count() as u32;
+    let floor = params.min_weight;
+
+    if floor.saturating_mul(Decimal::from_ratio(positive, 1u32)) >= Decimal::one() {
+        for s in scores.iter_mut().filter(|s| !s.score.is_zero()) {
+            s.weight = Decimal::from_ratio(1u32, positive);
+        }
+    } else {
+        // Move validators whose proportional share falls below the floor onto it until every
+        // remaining share clears it; each pass only shrinks the mass left for the others.
+        let mut floored = vec![false; scores.len()];
+        loop {
+            let pinned = floored.iter().filter(|f| **f).count() as u32;
+            let mass = Decimal::one() - floor * Decimal::from_ratio(pinned, 1u32);
+            let rest: Decimal = scores
+                .iter()
+                .zip(&floored)
+                .filter(|(_, f)| !**f)
+                .map(|(s, _)| s.score)
+                .sum();
+            let mut moved = false;
+            for (s, f) in scores.iter_mut().zip(floored.iter_mut()) {
+                if s.score.is_zero() {
+                    continue;
+                }
+                s.weight = if *f {
+                    floor
+                } else {
+                    mass * Decimal::from_ratio(s.score.atomics(), rest.atomics())
+                };
+                if !*f && s.weight < floor {
+                    *f = true;
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+    }
+
+    // Rounding leaves the sum short by a few atomics; give them to the highest score
+    let assigned: Decimal = scores.iter().map(|s| s.weight).sum();
+    if let Some(top) = scores.iter_mut().max_by_key(|s| s.score) {
+        top.weight += Decimal::one() - assigned;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{Timestamp, Uint128};
+
+    fn validator(address: &str, uptime: u64, commission: u64, slashes: u32) -> ValidatorInfo {
+        ValidatorInfo {
+            address: address.to_string(),
+            delegated_amount: Uint128::zero(),
+            weight: Decimal::zero(),
+            last_reward_claim: Timestamp::from_seconds(0),
+            slashing_events: slashes,
+            uptime_percentage: Decimal::percent(uptime),
+            commission_rate: Decimal::percent(commission),
+            is_active: true,
+            draining: false,
+        }
+    }
+
+    #[test]
+    fn test_score_breakdown() {
+        let params = ScoringParams::default();
+
+        let s = score_validator(&params, &validator("val1", 100, 10, 0), false);
+        assert_eq!(s.uptime_score, Decimal::percent(70));
+        assert_eq!(s.commission_score, Decimal::percent(27));
+        assert_eq!(s.score, Decimal::percent(97));
+
+        let s = score_validator(&params, &validator("val2", 100, 10, 2), false);
+        assert_eq!(s.slashing_penalty, Decimal::percent(50));
+        assert_eq!(s.score, Decimal::permille(485));
+
+        // Below the uptime floor and above the commission cap
+        assert!(score_validator(&params, &validator("val3", 80, 10, 0), false).score.is_zero());
+        assert!(score_validator(&params, &validator("val4", 100, 25, 0), false).score.is_zero());
+
+        // Stale oracle metrics
+        assert!(score_validator(&params, &validator("val1", 100, 10, 0), true).score.is_zero());
+    }
+
+    #[test]
+    fn test_assign_weights() {
+        let params = ScoringParams {
+            min_weight: Decimal::percent(40),
+            ..ScoringParams::default()
+        };
+        let mut scores = vec![
+            score_validator(&params, &validator("val1", 100, 0, 0), false),
+            score_validator(&params, &validator("val2", 100, 0, 3), false),
+            score_validator(&params, &validator("val3", 50, 0, 0), false),
+        ];
+        assign_weights(&params, &mut scores);
+
+        // val2's 20% share is raised to the floor and val1 keeps the rest
+        assert_eq!(scores[0].weight, Decimal::percent(60));
+        assert_eq!(scores[1].weight, Decimal::percent(40));
+        assert!(scores[2].weight.is_zero());
+        assert_eq!(scores.iter().map(|s| s.weight).sum::<Decimal>(), Decimal::one());
+
+        // Shares that need rounding still sum to one, and the floor holds through the second pass
+        let params = ScoringParams {
+            min_weight: Decimal::percent(30),
+            ..ScoringParams::default()
+        };
+        let mut scores = vec![
+            score_validator(&params, &validator("val1", 100, 0, 0), false),
+            score_validator(&params, &validator("val2", 100, 10, 0), false),
+            score_validator(&params, &validator("val3", 100, 0, 3), false),
+        ];
+        assign_weights(&params, &mut scores);
+        assert_eq!(scores[2].weight, Decimal::percent(30));
+        assert!(scores.iter().all(|s| s.weight >= params.min_weight));
+        assert_eq!(scores.iter().map(|s| s.weight).sum::<Decimal>(), Decimal::one());
+
+        // A floor that cannot be met for every validator weights them equally
+        let params = ScoringParams {
+            min_weight: Decimal::percent(50),
+            ..params
+        };
+        assign_weights(&params, &mut scores);
+        assert!(scores[1].weight >= Decimal::from_ratio(1u32, 3u32));
+        assert_eq!(scores[2].weight, Decimal::from_ratio(1u32, 3u32));
+        assert_eq!(scores.iter().map(|s| s.weight).sum::<Decimal>(), Decimal::one());
+    }
+}
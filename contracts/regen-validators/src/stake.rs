@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{
+    Addr, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult,
+    Storage, Timestamp, Uint128,
+};
+use regen_types::DelegationAmount;
+
+use crate::error::ContractError;
+use crate::history::record_snapshot;
+use crate::msg::{ConsumerStake, StakeSummaryResponse, ValidatorShare, ValidatorStakeResponse};
+use crate::state::{CONSUMERS, CONSUMER_STAKE, VALIDATORS};
+
+/// Consumer callback with its new stake for the validators an operation changed; validators it
+/// leaves out keep their last reported amount. Each validator's `delegated_amount` is the sum of
+/// the stake reported by all consumers.
+pub fn exec_delegation_report(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    delegations: Vec<DelegationAmount>,
+) -> Result<Response, ContractError> {
+    if !CONSUMERS.has(deps.storage, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut reported: BTreeMap<String, Uint128> = BTreeMap::new();
+    for d in delegations {
+        if reported.insert(d.validator.clone(), d.amount).is_some() {
+            return Err(ContractError::Std(StdError::generic_err(format!(
+                "duplicate delegation for {}",
+                d.validator
+            ))));
+        }
+    }
+
+    let mut updated = 0u32;
+    for (validator, amount) in reported {
+        if set_consumer_stake(deps.storage, &info.sender, &validator, amount, env.block.time)? {
+            updated += 1;
+        }
+    }
+    Ok(Response::new()
+        .add_attribute("action", "delegation_report")
+        .add_attribute("consumer", info.sender)
+        .add_attribute("updated", updated.to_string()))
+}
+
+/// Drop all stake reported by `consumer`, e.g. once it is no longer served
+pub fn clear_consumer_stake(
+    storage: &mut dyn Storage,
+    consumer: &Addr,
+    now: Timestamp,
+) -> StdResult<()> {
+    let validators = CONSUMER_STAKE
+        .prefix(consumer)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for validator in validators {
+        set_consumer_stake(storage, consumer, &validator, Uint128::zero(), now)?;
+    }
+    Ok(())
+}
+
+/// Record `amount` as the consumer's stake with `validator` and adjust the validator's total.
+/// Stake with validators unknown to the registry is ignored. Returns whether anything changed.
+fn set_consumer_stake(
+    storage: &mut dyn Storage,
+    consumer: &Addr,
+    validator: &str,
+    amount: Uint128,
+    now: Timestamp,
+) -> StdResult<bool> {
+    let Some(mut v) = VALIDATORS.may_load(storage, validator.to_string())? else {
+        return Ok(false);
+    };
+    let previous = CONSUMER_STAKE
+        .may_load(storage, (consumer, validator))?
+        .unwrap_or_default();
+    if previous == amount {
+        return Ok(false);
+    }
+    v.delegated_amount = v.delegated_amount.checked_sub(previous)?.checked_add(amount)?;
+    VALIDATORS.save(storage, validator.to_string(), &v)?;
+    record_snapshot(storage, &v, now)?;
+    if amount.is_zero() {
+        CONSUMER_STAKE.remove(storage, (consumer, validator));
+    } else {
+        CONSUMER_STAKE.save(storage, (consumer, validator), &amount)?;
+    }
+    Ok(true)
+}
+
+/// Herfindahl-Hirschman index (sum of squared shares, 1 for a single validator) and Nakamoto
+/// coefficient (fewest validators holding more than a third of the stake) of `amounts`
+pub fn concentration(amounts: &[Uint128]) -> (Decimal, u32) {
+    let total: Uint128 = amounts.iter().sum();
+    if total.is_zero() {
+        return (Decimal::zero(), 0);
+    }
+    let hhi = amounts
+        .iter()
+        .map(|a| {
+            let share = Decimal::from_ratio(*a, total);
+            share * share
+        })
+        .sum();
+
+    let mut sorted = amounts.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    let mut cumulative = Uint128::zero();
+    let mut nakamoto = 0u32;
+    for a in sorted {
+        cumulative += a;
+        nakamoto += 1;
+        if cumulative.full_mul(3u128) > total.into() {
+            break;
+        }
+    }
+    (hhi, nakamoto)
+}
+
+pub fn query_stake_summary(deps: Deps) -> StdResult<StakeSummaryResponse> {
+    let staked = VALIDATORS
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|r| match r {
+            Ok((address, v)) if !v.delegated_amount.is_zero() => {
+                Some(Ok((address, v.delegated_amount)))
+            }
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let amounts: Vec<Uint128> = staked.iter().map(|(_, a)| *a).collect();
+    let total_delegated: Uint128 = amounts.iter().sum();
+    let (hhi, nakamoto_coefficient) = concentration(&amounts);
+    let largest = staked
+        .iter()
+        .max_by_key(|(_, a)| *a)
+        .map(|(address, amount)| ValidatorShare {
+            address: address.clone(),
+            amount: *amount,
+            share: Decimal::from_ratio(*amount, total_delegated),
+        });
+
+    let mut consumers = Vec::new();
+    for consumer in CONSUMERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+    {
+        let amount = CONSUMER_STAKE
+            .prefix(&consumer)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|r| r.map(|(_, a)| a))
+            .sum::<StdResult<Uint128>>()?;
+        consumers.push(ConsumerStake {
+            consumer: consumer.to_string(),
+            amount,
+        });
+    }
+
+    Ok(StakeSummaryResponse {
+        total_delegated,
+        staked_validators: staked.len() as u32,
+        largest,
+        hhi,
+        nakamoto_coefficient,
+        consumers,
+    })
+}
+
+pub fn query_validator_stake(deps: Deps, address: String) -> StdResult<ValidatorStakeResponse> {
+    let v = VALIDATORS.load(deps.storage, address.clone())?;
+    let mut consumers = Vec::new();
+    for consumer in CONSUMERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?
+    {
+        if let Some(amount) = CONSUMER_STAKE.may_load(deps.storage, (&consumer, &address))? {
+            consumers.push(ConsumerStake {
+                consumer: consumer.to_string(),
+                amount,
+            });
+        }
+    }
+    Ok(ValidatorStakeResponse {
+        address,
+        delegated_amount: v.delegated_amount,
+        consumers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concentration_metrics() {
+        assert_eq!(concentration(&[]), (Decimal::zero(), 0));
+        assert_eq!(concentration(&[Uint128::new(5)]), (Decimal::one(), 1));
+
+        let even = vec![Uint128::new(100); 4];
+        assert_eq!(concentration(&even), (Decimal::percent(25), 2));
+
+        // 50 alone exceeds a third of 100
+        let skewed = [10u128, 50, 20, 20].map(Uint128::new);
+        assert_eq!(concentration(&skewed), (Decimal::percent(34), 1));
+    }
+}
@@ -1,11 +1,17 @@
-use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub admin: Addr,
+    /// Address authorized to submit per-epoch validator metrics
+    pub oracle: Option<Addr>,
+    /// Governance address allowed to approve or reject applications alongside the admin
+    pub governance: Option<Addr>,
+    /// Address allowed to mark validator profiles as verified
+    pub attester: Option<Addr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -18,7 +24,401 @@ pub struct ValidatorInfo {
     pub uptime_percentage: Decimal,
     pub commission_rate: Decimal,
     pub is_active: bool,
+    /// Pending removal; deleted once the hub reports it holds no stake with the validator
+    #[serde(default)]
+    pub draining: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorStatus {
+    Active,
+    Inactive,
+    Draining,
+}
+
+impl ValidatorStatus {
+    pub fn of(v: &ValidatorInfo) -> Self {
+        if v.draining {
+            ValidatorStatus::Draining
+        } else if v.is_active {
+            ValidatorStatus::Active
+        } else {
+            ValidatorStatus::Inactive
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidatorStatus::Active => "active",
+            ValidatorStatus::Inactive => "inactive",
+            ValidatorStatus::Draining => "draining",
+        }
+    }
+}
+
+pub struct ValidatorIndexes<'a> {
+    pub status: MultiIndex<'a, String, ValidatorInfo, String>,
+    /// Keyed by the weight's atomics so iteration follows weight order
+    pub weight: MultiIndex<'a, u128, ValidatorInfo, String>,
+    pub delegated: MultiIndex<'a, u128, ValidatorInfo, String>,
+}
+
+impl<'a> IndexList<ValidatorInfo> for ValidatorIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<ValidatorInfo>> + '_> {
+        let v: Vec<&dyn Index<ValidatorInfo>> = vec![&self.status, &self.weight, &self.delegated];
+        Box::new(v.into_iter())
+    }
+}
+
+fn status_index(_pk: &[u8], v: &ValidatorInfo) -> String {
+    ValidatorStatus::of(v).as_str().to_string()
+}
+
+fn weight_index(_pk: &[u8], v: &ValidatorInfo) -> u128 {
+    v.weight.atomics().u128()
+}
+
+fn delegated_index(_pk: &[u8], v: &ValidatorInfo) -> u128 {
+    v.delegated_amount.u128()
+}
+
+/// Coefficients and floors used to derive validator weights from performance metrics
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScoringParams {
+    /// Coefficient applied to `uptime_percentage`
+    pub uptime_coefficient: Decimal,
+    /// Coefficient applied to `1 - commission_rate`
+    pub commission_coefficient: Decimal,
+    /// Fraction of the score lost per slashing event, capped at the whole score
+    pub slashing_penalty: Decimal,
+    /// Validators below this uptime score zero
+    pub min_uptime: Decimal,
+    /// Validators above this commission score zero
+    pub max_commission: Decimal,
+    /// Minimum weight given to any validator with a positive score
+    pub min_weight: Decimal,
+}
+
+impl Default for ScoringParams {
+    fn default() -> Self {
+        ScoringParams {
+            uptime_coefficient: Decimal::percent(70),
+            commission_coefficient: Decimal::percent(30),
+            slashing_penalty: Decimal::percent(25),
+            min_uptime: Decimal::percent(90),
+            max_commission: Decimal::percent(20),
+            min_weight: Decimal::zero(),
+        }
+    }
+}
+
+/// Sanity bounds applied to oracle submissions
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleParams {
+    /// Metrics older than this (seconds) make a validator ineligible for scoring
+    pub max_staleness: u64,
+    /// Largest accepted uptime move between two consecutive submissions
+    pub max_uptime_change: Decimal,
+    /// Consecutive reports, each within `max_uptime_change` of the one before, after which a
+    /// larger move is accepted as a genuine change
+    #[serde(default = "default_outlier_confirmations")]
+    pub outlier_confirmations: u32,
+}
+
+fn default_outlier_confirmations() -> u32 {
+    3
+}
+
+impl Default for OracleParams {
+    fn default() -> Self {
+        OracleParams {
+            max_staleness: 3 * 24 * 60 * 60,
+            max_uptime_change: Decimal::percent(20),
+            outlier_confirmations: default_outlier_confirmations(),
+        }
+    }
+}
+
+/// Uptime reports rejected as outliers since the validator's last accepted metrics
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UptimeOutlier {
+    /// Most recent rejected uptime
+    pub uptime_percentage: Decimal,
+    /// Consecutive rejected reports agreeing with it
+    pub reports: u32,
+}
+
+/// Latest oracle-reported metrics for a validator
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MetricsRecord {
+    pub epoch: u64,
+    pub uptime_percentage: Decimal,
+    pub missed_blocks: u64,
+    pub slashing_events: u32,
+    /// Share of total network voting power, if reported
+    pub voting_power: Option<Decimal>,
+    /// Operator self-delegation, if reported
+    pub self_bond: Option<Uint128>,
+    /// Position by voting power in the bonded set (1 = largest), if reported
+    pub voting_power_rank: Option<u32>,
+    pub updated_at: Timestamp,
+}
+
+/// Network-wide figures reported alongside validator metrics
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NetworkStats {
+    pub epoch: u64,
+    pub bonded_tokens: Uint128,
+    pub updated_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RejectedMetrics {
+    pub address: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MetricsSubmission {
+    pub epoch: u64,
+    pub submitter: Addr,
+    pub submitted_at: Timestamp,
+    pub accepted: u32,
+    pub rejected: Vec<RejectedMetrics>,
+}
+
+/// Eligibility rules checked against the chain when a validator applies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ApplicationRules {
+    /// Deposit required with an application, refunded when it leaves the queue
+    pub deposit: Option<Coin>,
+    /// Highest accepted on-chain commission and commission commitment
+    pub max_commission: Decimal,
+    /// Highest accepted on-chain maximum daily commission change
+    pub max_commission_change_rate: Decimal,
+}
+
+impl Default for ApplicationRules {
+    fn default() -> Self {
+        ApplicationRules {
+            deposit: None,
+            max_commission: Decimal::percent(20),
+            max_commission_change_rate: Decimal::percent(5),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Application {
+    pub applicant: Addr,
+    pub address: String,
+    pub moniker: String,
+    pub website: Option<String>,
+    pub details: Option<String>,
+    /// Commission the validator commits not to exceed while listed
+    pub commission_commitment: Decimal,
+    pub deposit: Vec<Coin>,
+    pub submitted_at: Timestamp,
+}
+
+/// Hard eligibility rules; a `None` limit disables that rule
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EligibilityPolicy {
+    pub max_commission: Option<Decimal>,
+    pub min_uptime: Option<Decimal>,
+    pub max_slashing_events: Option<u32>,
+    /// Highest share of network voting power, checked against oracle metrics
+    pub max_voting_power: Option<Decimal>,
+    /// Lowest operator self-delegation, checked against oracle metrics
+    pub min_self_bond: Option<Uint128>,
+    /// Seconds a validator may stay in violation before it is deactivated
+    pub grace_period: u64,
+}
+
+impl Default for EligibilityPolicy {
+    fn default() -> Self {
+        EligibilityPolicy {
+            max_commission: None,
+            min_uptime: None,
+            max_slashing_events: None,
+            max_voting_power: None,
+            min_self_bond: None,
+            grace_period: 3 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRule {
+    MaxCommission,
+    MinUptime,
+    MaxSlashingEvents,
+    MaxVotingPower,
+    MinSelfBond,
+}
+
+impl PolicyRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyRule::MaxCommission => "max_commission",
+            PolicyRule::MinUptime => "min_uptime",
+            PolicyRule::MaxSlashingEvents => "max_slashing_events",
+            PolicyRule::MaxVotingPower => "max_voting_power",
+            PolicyRule::MinSelfBond => "min_self_bond",
+        }
+    }
+}
+
+/// Open policy violation recorded by `EvaluatePolicy`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PolicyViolation {
+    pub rules: Vec<PolicyRule>,
+    /// First evaluation that found the validator in violation
+    pub since: Timestamp,
+}
+
+/// Validator state at a point in time, recorded whenever it changes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorSnapshot {
+    pub timestamp: Timestamp,
+    pub weight: Decimal,
+    pub commission_rate: Decimal,
+    pub uptime_percentage: Decimal,
+    pub slashing_events: u32,
+    pub delegated_amount: Uint128,
+    pub is_active: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryParams {
+    /// Snapshots older than this (seconds) are pruned
+    pub retention: u64,
+}
+
+impl Default for HistoryParams {
+    fn default() -> Self {
+        HistoryParams {
+            retention: 90 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// Per-epoch limits on weight changes; changes beyond them are queued as targets
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WeightLimits {
+    /// Epoch length in seconds
+    pub epoch: u64,
+    /// Largest change of a single validator's weight per epoch; unlimited if unset
+    pub max_change: Option<Decimal>,
+    /// Largest sum of weight changes across the set per epoch; unlimited if unset
+    pub max_total_change: Option<Decimal>,
+}
+
+impl Default for WeightLimits {
+    fn default() -> Self {
+        WeightLimits {
+            epoch: 24 * 60 * 60,
+            max_change: None,
+            max_total_change: None,
+        }
+    }
+}
+
+/// Weight change consumed within an epoch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct EpochUsage {
+    pub epoch: u64,
+    pub changed: Decimal,
+}
+
+/// Reference to evidence of an ecological commitment
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Attestation {
+    /// Category used for filtering, e.g. "renewable_energy" or "ecocredit_retirement"
+    pub kind: String,
+    /// Evidence reference, typically a Regen data-module IRI
+    pub iri: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Verification {
+    pub attester: Addr,
+    pub verified_at: Timestamp,
+}
+
+/// Self-managed validator metadata
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorProfile {
+    /// Account allowed to update the profile
+    pub manager: Addr,
+    pub moniker: String,
+    pub website: Option<String>,
+    pub details: Option<String>,
+    pub region: Option<String>,
+    pub hosting: Option<String>,
+    pub attestations: Vec<Attestation>,
+    /// Set by the attester; cleared whenever the manager edits the profile
+    pub verification: Option<Verification>,
+    pub updated_at: Timestamp,
+}
+
+/// Liquid staking hub served by the registry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Consumer {
+    pub name: String,
+    /// Validators this consumer delegates to; `None` follows the whole shared active set
+    pub validators: Option<Vec<String>>,
+}
+
+/// Last failed push of the active set to a consumer
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PushFailure {
+    pub error: String,
+    pub time: Timestamp,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const VALIDATORS: Map<String, ValidatorInfo> = Map::new("validators");
\ No newline at end of file
+/// Validators by address, indexed by status, weight and delegated amount
+pub const VALIDATORS: IndexedMap<String, ValidatorInfo, ValidatorIndexes> = IndexedMap::new(
+    "validators",
+    ValidatorIndexes {
+        status: MultiIndex::new(status_index, "validators", "validators__status"),
+        weight: MultiIndex::new(weight_index, "validators", "validators__weight"),
+        delegated: MultiIndex::new(delegated_index, "validators", "validators__delegated"),
+    },
+);
+pub const SCORING: Item<ScoringParams> = Item::new("scoring");
+pub const ORACLE_PARAMS: Item<OracleParams> = Item::new("oracle_params");
+pub const VALIDATOR_METRICS: Map<String, MetricsRecord> = Map::new("validator_metrics");
+pub const UPTIME_OUTLIERS: Map<String, UptimeOutlier> = Map::new("uptime_outliers");
+pub const NETWORK_STATS: Item<NetworkStats> = Item::new("network_stats");
+pub const METRICS_SUBMISSIONS: Map<u64, MetricsSubmission> = Map::new("metrics_submissions");
+pub const APPLICATION_RULES: Item<ApplicationRules> = Item::new("application_rules");
+pub const APPLICATIONS: Map<String, Application> = Map::new("applications");
+pub const POLICY: Item<EligibilityPolicy> = Item::new("policy");
+pub const POLICY_VIOLATIONS: Map<String, PolicyViolation> = Map::new("policy_violations");
+pub const HISTORY_PARAMS: Item<HistoryParams> = Item::new("history_params");
+pub const WEIGHT_LIMITS: Item<WeightLimits> = Item::new("weight_limits");
+/// Weights not reached yet because of the limits, phased in over the following epochs
+pub const WEIGHT_TARGETS: Map<String, Decimal> = Map::new("weight_targets");
+pub const WEIGHT_USAGE: Map<String, EpochUsage> = Map::new("weight_usage");
+pub const SET_WEIGHT_USAGE: Item<EpochUsage> = Item::new("set_weight_usage");
+/// Per-validator snapshots keyed by (address, timestamp in seconds)
+pub const VALIDATOR_HISTORY: Map<(&str, u64), ValidatorSnapshot> = Map::new("validator_history");
+pub const PROFILES: Map<String, ValidatorProfile> = Map::new("profiles");
+/// Registered consumers, notified of changes to their view of the active set
+pub const CONSUMERS: Map<&Addr, Consumer> = Map::new("consumers");
+/// Most recent failed `RegistryUpdate` push per consumer
+pub const PUSH_FAILURES: Map<&Addr, PushFailure> = Map::new("push_failures");
+/// Consumer each push of the current transaction was sent to, keyed by reply id offset. Every
+/// push writes its entry before it is dispatched, so entries left by earlier transactions are
+/// never read.
+pub const PUSH_TARGETS: Map<u64, Addr> = Map::new("push_targets");
+/// Per-consumer weight overrides keyed by (consumer, validator)
+pub const CONSUMER_WEIGHTS: Map<(&Addr, &str), Decimal> = Map::new("consumer_weights");
+/// Consumers that still have to report a draining validator as drained, keyed by (validator, consumer)
+pub const PENDING_DRAINS: Map<(&str, &Addr), bool> = Map::new("pending_drains");
+/// Stake each consumer last reported per validator, keyed by (consumer, validator)
+pub const CONSUMER_STAKE: Map<(&Addr, &str), Uint128> = Map::new("consumer_stake");
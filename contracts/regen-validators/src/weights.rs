@@ -0,0 +1,351 @@
+use cosmwasm_std::{
+    Decimal, Deps, DepsMut, Env, Event, Order, Response, StdError, StdResult, Storage, Timestamp,
+};
+use cw_storage_plus::Bound;
+use regen_types::validate_weights;
+
+use crate::consumers::consumer_update_msgs;
+use crate::error::ContractError;
+use crate::history::record_snapshot;
+use crate::msg::{ValidatorWeight, WeightScheduleEntry, WeightScheduleResponse};
+use crate::state::{
+    EpochUsage, ValidatorInfo, ValidatorStatus, WeightLimits, SET_WEIGHT_USAGE, VALIDATORS,
+    WEIGHT_LIMITS, WEIGHT_TARGETS, WEIGHT_USAGE,
+};
+
+/// Most validators accepted by a single `SetWeights`
+const MAX_WEIGHT_SET: usize = 200;
+
+/// Largest step from `current` towards `target` within both remaining allowances
+pub fn weight_step(
+    current: Decimal,
+    target: Decimal,
+    validator_remaining: Option<Decimal>,
+    set_remaining: Option<Decimal>,
+) -> Decimal {
+    let distance = current.abs_diff(target);
+    let step = [validator_remaining, set_remaining]
+        .into_iter()
+        .flatten()
+        .fold(distance, Decimal::min);
+    if target > current {
+        current + step
+    } else {
+        current - step
+    }
+}
+
+fn remaining(limit: Option<Decimal>, usage: &EpochUsage, epoch: u64) -> Option<Decimal> {
+    let used = if usage.epoch == epoch { usage.changed } else { Decimal::zero() };
+    limit.map(|l| l.saturating_sub(used))
+}
+
+fn consume(usage: Option<EpochUsage>, epoch: u64, change: Decimal) -> EpochUsage {
+    match usage {
+        Some(u) if u.epoch == epoch => EpochUsage {
+            epoch,
+            changed: u.changed + change,
+        },
+        _ => EpochUsage {
+            epoch,
+            changed: change,
+        },
+    }
+}
+
+/// Move `v` towards `target` as far as this epoch's limits allow and queue the rest.
+/// Saves the validator and a snapshot when its weight changed; returns whether it did.
+pub fn move_weight(
+    storage: &mut dyn Storage,
+    v: &mut ValidatorInfo,
+    target: Decimal,
+    now: Timestamp,
+) -> StdResult<bool> {
+    let limits = WEIGHT_LIMITS.load(storage)?;
+    let epoch = now.seconds() / limits.epoch;
+    let usage = WEIGHT_USAGE.may_load(storage, v.address.clone())?;
+    let set_usage = SET_WEIGHT_USAGE.may_load(storage)?;
+    let weight = weight_step(
+        v.weight,
+        target,
+        remaining(limits.max_change, &usage.clone().unwrap_or_default(), epoch),
+        remaining(limits.max_total_change, &set_usage.clone().unwrap_or_default(), epoch),
+    );
+
+    if weight == target {
+        WEIGHT_TARGETS.remove(storage, v.address.clone());
+    } else {
+        WEIGHT_TARGETS.save(storage, v.address.clone(), &target)?;
+    }
+    if weight == v.weight {
+        return Ok(false);
+    }
+    let change = weight.abs_diff(v.weight);
+    WEIGHT_USAGE.save(storage, v.address.clone(), &consume(usage, epoch, change))?;
+    SET_WEIGHT_USAGE.save(storage, &consume(set_usage, epoch, change))?;
+    v.weight = weight;
+    VALIDATORS.save(storage, v.address.clone(), v)?;
+    record_snapshot(storage, v, now)?;
+    Ok(true)
+}
+
+/// Phase queued targets in as far as the current epoch allows (permissionless)
+pub fn exec_advance_weights(mut deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let targets = WEIGHT_TARGETS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut updated = 0u32;
+    for (address, target) in targets {
+        let mut v = VALIDATORS.load(deps.storage, address)?;
+        if move_weight(deps.storage, &mut v, target, env.block.time)? {
+            updated += 1;
+        }
+    }
+    let mut res = Response::new();
+    if updated > 0 {
+        res = res.add_submessages(consumer_update_msgs(deps.branch())?);
+    }
+    Ok(res
+        .add_attribute("action", "advance_weights")
+        .add_attribute("updated", updated.to_string())
+        .add_attribute(
+            "queued",
+            WEIGHT_TARGETS
+                .keys(deps.storage, None, None, Order::Ascending)
+                .count()
+                .to_string(),
+        ))
+}
+
+pub fn exec_set_weights(
+    deps: DepsMut,
+    env: Env,
+    weights: Vec<ValidatorWeight>,
+    normalize: bool,
+) -> Result<Response, ContractError> {
+    let weights = validate_weights(&weights, MAX_WEIGHT_SET, normalize)?;
+    let active = VALIDATORS
+        .idx
+        .status
+        .prefix(ValidatorStatus::Active.as_str().to_string())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    if let Some(w) = weights.iter().find(|w| !active.contains(&w.address)) {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "{} is not an active validator",
+            w.address
+        ))));
+    }
+    if weights.len() != active.len() {
+        return Err(ContractError::Std(StdError::generic_err(format!(
+            "weights must cover all {} active validators",
+            active.len()
+        ))));
+    }
+
+    let mut updated = 0u32;
+    for w in &weights {
+        let mut v = VALIDATORS.load(deps.storage, w.address.clone())?;
+        if move_weight(deps.storage, &mut v, w.weight, env.block.time)? {
+            updated += 1;
+        }
+    }
+    let total: Decimal = weights.iter().map(|w| w.weight).sum();
+    let event = Event::new("weights_set")
+        .add_attribute("count", weights.len().to_string())
+        .add_attribute("normalized", normalize.to_string())
+        .add_attribute("total", total.to_string())
+        .add_attribute(
+            "weights",
+            weights
+                .iter()
+                .map(|w| format!("{}={}", w.address, w.weight))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+
+    let mut res = Response::new();
+    if updated > 0 {
+        res = res.add_submessages(consumer_update_msgs(deps)?);
+    }
+    Ok(res
+        .add_event(event)
+        .add_attribute("action", "set_weights")
+        .add_attribute("updated", updated.to_string()))
+}
+
+pub fn exec_update_weight_limits(
+    deps: DepsMut,
+    limits: WeightLimits,
+) -> Result<Response, ContractError> {
+    if limits.epoch == 0 {
+        return Err(ContractError::Std(StdError::generic_err("epoch must be positive")));
+    }
+    WEIGHT_LIMITS.save(deps.storage, &limits)?;
+    Ok(Response::new().add_attribute("action", "update_weight_limits"))
+}
+
+pub fn query_weight_schedule(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<WeightScheduleResponse> {
+    let limits = WEIGHT_LIMITS.load(deps.storage)?;
+    let epoch = env.block.time.seconds() / limits.epoch;
+    let set_usage = SET_WEIGHT_USAGE.may_load(deps.storage)?.unwrap_or_default();
+    let limit = limit.unwrap_or(50).min(200) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let entries = WEIGHT_TARGETS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|r| {
+            let (address, target) = r?;
+            let weight = VALIDATORS.load(deps.storage, address.clone())?.weight;
+            // Whole epochs at the per-validator limit, not counting what is left of this one
+            let epochs_remaining = limits.max_change.filter(|m| !m.is_zero()).map(|m| {
+                let distance = weight.abs_diff(target);
+                let epochs = distance.atomics().u128().div_ceil(m.atomics().u128());
+                epochs as u64
+            });
+            Ok(WeightScheduleEntry {
+                address,
+                weight,
+                target,
+                epochs_remaining,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(WeightScheduleResponse {
+        set_remaining: remaining(limits.max_total_change, &set_usage, epoch),
+        limits,
+        epoch,
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+
+    use crate::helpers::new_validator_info;
+    use crate::state::HISTORY_PARAMS;
+
+    fn save_validators(storage: &mut dyn Storage, weights: &[(&str, u64)]) {
+        HISTORY_PARAMS.save(storage, &Default::default()).unwrap();
+        for (address, weight) in weights {
+            let v = new_validator_info(
+                address.to_string(),
+                Decimal::percent(*weight),
+                Decimal::percent(5),
+                Timestamp::from_seconds(0),
+            );
+            VALIDATORS.save(storage, address.to_string(), &v).unwrap();
+        }
+    }
+
+    fn weight(deps: Deps, address: &str) -> Decimal {
+        VALIDATORS.load(deps.storage, address.to_string()).unwrap().weight
+    }
+
+    #[test]
+    fn steps_are_capped_by_both_allowances() {
+        let one = Decimal::one();
+        let pct = Decimal::percent;
+        assert_eq!(weight_step(one, Decimal::zero(), None, None), Decimal::zero());
+        assert_eq!(weight_step(one, Decimal::zero(), Some(pct(10)), None), pct(90));
+        assert_eq!(weight_step(pct(20), pct(80), Some(pct(30)), Some(pct(5))), pct(25));
+        // A step never overshoots the target
+        assert_eq!(weight_step(pct(20), pct(25), Some(pct(30)), None), pct(25));
+        assert_eq!(weight_step(pct(20), pct(80), Some(Decimal::zero()), None), pct(20));
+    }
+
+    #[test]
+    fn changes_beyond_the_limits_are_queued() {
+        let mut deps = mock_dependencies();
+        save_validators(deps.as_mut().storage, &[("val1", 100), ("val2", 0)]);
+        let err = exec_update_weight_limits(
+            deps.as_mut(),
+            WeightLimits {
+                epoch: 0,
+                ..WeightLimits::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("epoch must be positive"));
+        let limits = WeightLimits {
+            epoch: 100,
+            max_change: Some(Decimal::percent(30)),
+            max_total_change: Some(Decimal::percent(40)),
+        };
+        exec_update_weight_limits(deps.as_mut(), limits).unwrap();
+
+        // 100% -> 0% moves by the per-validator limit and queues the rest
+        let now = Timestamp::from_seconds(1_000);
+        let mut v = VALIDATORS.load(deps.as_ref().storage, "val1".to_string()).unwrap();
+        assert!(move_weight(deps.as_mut().storage, &mut v, Decimal::zero(), now).unwrap());
+        assert_eq!(weight(deps.as_ref(), "val1"), Decimal::percent(70));
+        let queued = WEIGHT_TARGETS.load(deps.as_ref().storage, "val1".to_string()).unwrap();
+        assert_eq!(queued, Decimal::zero());
+
+        // Only 10% of the set allowance is left this epoch
+        let mut v = VALIDATORS.load(deps.as_ref().storage, "val2".to_string()).unwrap();
+        move_weight(deps.as_mut().storage, &mut v, Decimal::percent(100), now).unwrap();
+        assert_eq!(weight(deps.as_ref(), "val2"), Decimal::percent(10));
+        let mut v = VALIDATORS.load(deps.as_ref().storage, "val2".to_string()).unwrap();
+        assert!(!move_weight(deps.as_mut().storage, &mut v, Decimal::percent(100), now).unwrap());
+
+        // Advancing within the same epoch changes nothing; the next epoch phases in more
+        let mut env = mock_env();
+        env.block.time = now;
+        let res = exec_advance_weights(deps.as_mut(), env.clone()).unwrap();
+        assert_eq!(res.attributes[1].value, "0");
+        env.block.time = now.plus_seconds(100);
+        exec_advance_weights(deps.as_mut(), env).unwrap();
+        assert_eq!(weight(deps.as_ref(), "val1"), Decimal::percent(40));
+        assert_eq!(weight(deps.as_ref(), "val2"), Decimal::percent(20));
+    }
+
+    #[test]
+    fn invalid_weight_sets_are_rejected_whole() {
+        let mut deps = mock_dependencies();
+        save_validators(deps.as_mut().storage, &[("val1", 50), ("val2", 50), ("val3", 0)]);
+        WEIGHT_LIMITS.save(deps.as_mut().storage, &WeightLimits::default()).unwrap();
+        let mut val3 = VALIDATORS.load(deps.as_ref().storage, "val3".to_string()).unwrap();
+        val3.is_active = false;
+        VALIDATORS.save(deps.as_mut().storage, "val3".to_string(), &val3).unwrap();
+        let set = |weights: &[(&str, u64)]| -> Vec<ValidatorWeight> {
+            weights
+                .iter()
+                .map(|(address, weight)| ValidatorWeight {
+                    address: address.to_string(),
+                    weight: Decimal::percent(*weight),
+                })
+                .collect()
+        };
+
+        for (weights, error) in [
+            (set(&[("val1", 60), ("val1", 40)]), "Duplicate validator val1"),
+            (set(&[("val1", 100), ("val2", 0)]), "Weight of val2 must be positive"),
+            (set(&[("val1", 60), ("val3", 40)]), "val3 is not an active validator"),
+            (set(&[("val1", 100)]), "weights must cover all 2 active validators"),
+        ] {
+            let err = exec_set_weights(deps.as_mut(), mock_env(), weights, true).unwrap_err();
+            assert!(err.to_string().contains(error), "{}", err);
+        }
+        let too_many = (0..=MAX_WEIGHT_SET)
+            .map(|i| ValidatorWeight {
+                address: format!("val{}", i),
+                weight: Decimal::percent(1),
+            })
+            .collect();
+        exec_set_weights(deps.as_mut(), mock_env(), too_many, true).unwrap_err();
+        assert_eq!(weight(deps.as_ref(), "val1"), Decimal::percent(50));
+
+        exec_set_weights(deps.as_mut(), mock_env(), set(&[("val1", 3), ("val2", 1)]), true)
+            .unwrap();
+        assert_eq!(weight(deps.as_ref(), "val1"), Decimal::percent(75));
+        assert_eq!(weight(deps.as_ref(), "val2"), Decimal::percent(25));
+    }
+}
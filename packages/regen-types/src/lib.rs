@@ -1,7 +1,12 @@
 pub mod validator;
 pub mod staking;
 pub mod rewards;
+pub mod registry;
 
-pub use validator::{ValidatorParams, ValidatorInfoView};
-pub use staking::{UnbondingRequestView, ExchangeRateView};
-pub use rewards::{RewardRecordView, ClaimRecordView};
\ No newline at end of file
+pub use validator::{validate_weights, ValidatorInfoView, ValidatorParams, ValidatorTag};
+pub use staking::{ExchangeRateView, StakingHookEvent, StakingHookMsg, UnbondingRequestView};
+pub use rewards::{RewardRecordView, ClaimRecordView, RewardsExecuteMsg};
+pub use registry::{
+    ActiveValidatorsResponse, DelegationAmount, HubExecuteMsg, RegistryExecuteMsg,
+    RegistryQueryMsg, ValidatorVotingPower, VotingPowerResponse,
+};
\ No newline at end of file
@@ -0,0 +1,64 @@
+use cosmwasm_std::{Decimal, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::validator::ValidatorParams;
+
+/// Queries the liquid staking hub issues against the `regen-validators` registry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryQueryMsg {
+    /// Shared active validators and their weights
+    ActiveValidators {},
+    /// The active set as seen by `consumer`, with its restrictions and weight overrides
+    ConsumerValidators { consumer: String },
+    /// Oracle-reported network voting power of validators with fresh metrics
+    VotingPower {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ActiveValidatorsResponse {
+    pub validators: Vec<ValidatorParams>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingPowerResponse {
+    /// Total bonded tokens on the network at the last report
+    pub bonded_tokens: Option<Uint128>,
+    pub validators: Vec<ValidatorVotingPower>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorVotingPower {
+    pub address: String,
+    /// Share of total network voting power
+    pub voting_power: Option<Decimal>,
+    /// Position by voting power in the bonded set, 1 being the largest
+    pub rank: Option<u32>,
+}
+
+/// Messages the registry sends to each consumer hub
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HubExecuteMsg {
+    /// Replace the hub's active validator set and rebalance towards the new weights
+    RegistryUpdate { validators: Vec<ValidatorParams> },
+}
+
+/// Messages a consumer hub sends back to the registry
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryExecuteMsg {
+    /// The hub no longer holds any stake with a validator that left its active set
+    ValidatorDrained { address: String },
+    /// The hub's new delegations to the validators an operation changed; validators not listed
+    /// keep their last reported amount and zero clears one. Sent after every change to the
+    /// hub's delegations, including slashing reconciliations.
+    DelegationReport { delegations: Vec<DelegationAmount> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationAmount {
+    pub validator: String,
+    pub amount: Uint128,
+}
@@ -15,4 +15,11 @@ pub struct ClaimRecordView {
     pub user: Addr,
     pub amount: Uint128,
     pub timestamp: Timestamp,
-}
\ No newline at end of file
+}
+/// Messages the liquid staking hub sends to the `regen-rewards` ledger as its distributor
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardsExecuteMsg {
+    RecordReward { validator: String, amount: Uint128 },
+    RecordClaim { user: String, amount: Uint128 },
+}
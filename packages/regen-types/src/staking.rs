@@ -13,4 +13,34 @@ pub struct UnbondingRequestView {
     pub dregen_amount: Uint128,
     pub regen_amount: Uint128,
     pub completion_time: u64,
-}
\ No newline at end of file
+}
+/// Execute message the liquid staking hub sends to each registered hook contract. Receivers add
+/// a matching `staking_hook` variant to their own `ExecuteMsg`. The rate and totals are the
+/// values after the change.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StakingHookMsg {
+    StakingHook {
+        event: StakingHookEvent,
+        exchange_rate: Decimal,
+        total_regen_staked: Uint128,
+        total_dregen_supply: Uint128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StakingHookEvent {
+    Stake {
+        user: String,
+        regen_amount: Uint128,
+        dregen_amount: Uint128,
+    },
+    Unbond {
+        user: String,
+        dregen_amount: Uint128,
+        regen_amount: Uint128,
+    },
+    RewardsHarvested { amount: Uint128 },
+    Slashed { loss: Uint128 },
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::{Decimal, Timestamp, Uint128};
+use cosmwasm_std::{Decimal, StdError, StdResult, Timestamp, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +8,70 @@ pub struct ValidatorParams {
     pub weight: Decimal,
 }
 
+/// Free-form validator label used for diversity constraints,
+/// e.g. `region=eu-west`, `provider=hetzner` or `community=true`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ValidatorTag {
+    pub key: String,
+    pub value: String,
+}
+
+/// Check a whole weight set: at most `max_count` entries, no duplicate addresses and only
+/// positive weights. With `normalize`, weights are scaled to sum to exactly one.
+pub fn validate_weights(
+    weights: &[ValidatorParams],
+    max_count: usize,
+    normalize: bool,
+) -> StdResult<Vec<ValidatorParams>> {
+    if weights.len() > max_count {
+        return Err(StdError::generic_err(format!(
+            "Weight set exceeds {} validators",
+            max_count
+        )));
+    }
+    for (i, w) in weights.iter().enumerate() {
+        if w.weight.is_zero() {
+            return Err(StdError::generic_err(format!("Weight of {} must be positive", w.address)));
+        }
+        if weights[..i].iter().any(|other| other.address == w.address) {
+            return Err(StdError::generic_err(format!("Duplicate validator {}", w.address)));
+        }
+    }
+    if !normalize {
+        return Ok(weights.to_vec());
+    }
+
+    let total = weights
+        .iter()
+        .try_fold(Decimal::zero(), |acc, w| acc.checked_add(w.weight))?;
+    let mut remaining = Decimal::one();
+    let mut normalized = Vec::with_capacity(weights.len());
+    for (i, w) in weights.iter().enumerate() {
+        // The last weight absorbs rounding so the set sums to exactly one
+        let weight = if i == weights.len() - 1 {
+            remaining
+        } else {
+            let share = w
+                .weight
+                .checked_div(total)
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            remaining = remaining.checked_sub(share)?;
+            share
+        };
+        if weight.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "Weight of {} rounds to zero after normalization",
+                w.address
+            )));
+        }
+        normalized.push(ValidatorParams {
+            address: w.address.clone(),
+            weight,
+        });
+    }
+    Ok(normalized)
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ValidatorInfoView {
     pub address: String,
@@ -18,4 +82,40 @@ pub struct ValidatorInfoView {
     pub uptime_percentage: Decimal,
     pub commission_rate: Decimal,
     pub is_active: bool,
-}
\ No newline at end of file
+    pub draining: bool,
+    pub tags: Vec<ValidatorTag>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(entries: &[(&str, u64)]) -> Vec<ValidatorParams> {
+        entries
+            .iter()
+            .map(|(address, pct)| ValidatorParams {
+                address: address.to_string(),
+                weight: Decimal::percent(*pct),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn weight_sets_are_validated_and_normalized() {
+        assert_eq!(validate_weights(&[], 10, true).unwrap(), vec![]);
+        assert!(validate_weights(&params(&[("a", 10), ("a", 20)]), 10, false).is_err());
+        assert!(validate_weights(&params(&[("a", 10), ("b", 0)]), 10, false).is_err());
+        assert!(validate_weights(&params(&[("a", 10), ("b", 20)]), 1, false).is_err());
+
+        let set = params(&[("a", 100), ("b", 300)]);
+        assert_eq!(validate_weights(&set, 10, false).unwrap(), set);
+        assert_eq!(
+            validate_weights(&set, 10, true).unwrap(),
+            params(&[("a", 25), ("b", 75)])
+        );
+
+        let thirds = validate_weights(&params(&[("a", 1), ("b", 1), ("c", 1)]), 10, true).unwrap();
+        let total: Decimal = thirds.iter().map(|w| w.weight).sum();
+        assert_eq!(total, Decimal::one());
+    }
+}